@@ -1 +1,33 @@
 example-row = Example row
+
+clock-error = Error!
+use-suggestion = Use { $name }
+
+tz-error-no-database = No timezone database found
+tz-error-not-chosen = Please choose a timezone
+tz-error-not-found = No timezone called { $name }
+tz-error-not-found-suggestion = No timezone called { $name }, did you mean { $suggestion }?
+tz-error-permission-denied = Not allowed to read timezone { $name }
+tz-error-corrupt = The file for timezone { $name } is damaged
+tz-error-unsupported = The file for timezone { $name } is in a format World Clocks can't read
+tz-error-invalid-name = { $name } isn't a timezone, UTC offset or POSIX TZ string
+tz-error-outside-root = { $name } points outside the timezone database, so it was not read
+tz-error-io = Couldn't read timezone { $name }: { $reason }
+
+config-read-error = Some settings couldn't be read, so defaults are used for them: { $reason }
+config-write-error = Couldn't save the default settings: { $reason }
+
+transition-upcoming = { $name } moves to { $abbreviation } in { $time } ({ $change })
+transition-recent = { $name } moved to { $abbreviation } { $time } ago ({ $change })
+duration-minutes = { $count ->
+    [one] { $count } minute
+   *[other] { $count } minutes
+}
+duration-hours = { $count ->
+    [one] { $count } hour
+   *[other] { $count } hours
+}
+duration-days = { $count ->
+    [one] { $count } day
+   *[other] { $count } days
+}
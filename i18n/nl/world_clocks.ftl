@@ -1 +1,31 @@
 example-row = Voorbeeldregel
+
+clock-error = Fout!
+use-suggestion = { $name } gebruiken
+
+tz-error-no-database = Geen tijdzonedatabase gevonden
+tz-error-not-chosen = Kies een tijdzone
+tz-error-not-found = Geen tijdzone met de naam { $name }
+tz-error-not-found-suggestion = Geen tijdzone met de naam { $name }, bedoelde je { $suggestion }?
+tz-error-permission-denied = Geen toestemming om tijdzone { $name } te lezen
+tz-error-corrupt = Het bestand voor tijdzone { $name } is beschadigd
+tz-error-unsupported = Het bestand voor tijdzone { $name } heeft een indeling die World Clocks niet kan lezen
+tz-error-invalid-name = { $name } is geen tijdzone, UTC-verschuiving of POSIX TZ-tekenreeks
+tz-error-outside-root = { $name } verwijst naar een plek buiten de tijdzonedatabase en is niet gelezen
+tz-error-io = Kon tijdzone { $name } niet lezen: { $reason }
+
+config-read-error = Sommige instellingen konden niet gelezen worden, daarvoor worden de standaardwaarden gebruikt: { $reason }
+config-write-error = De standaardinstellingen konden niet opgeslagen worden: { $reason }
+
+transition-upcoming = { $name } gaat over { $time } naar { $abbreviation } ({ $change })
+transition-recent = { $name } is { $time } geleden naar { $abbreviation } gegaan ({ $change })
+duration-minutes = { $count ->
+    [one] { $count } minuut
+   *[other] { $count } minuten
+}
+# "uur" doesn't change after a number.
+duration-hours = { $count } uur
+duration-days = { $count ->
+    [one] { $count } dag
+   *[other] { $count } dagen
+}
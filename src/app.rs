@@ -4,17 +4,20 @@ use std::sync::LazyLock;
 
 use chrono::DurationRound;
 use cosmic::app::{Core, Task};
-use cosmic::iced::futures::SinkExt;
-use cosmic::iced::{stream, window, Alignment, Length, Limits, Subscription};
+use cosmic::cosmic_config::{self, ConfigGet, CosmicConfigEntry};
+use cosmic::iced::futures::channel::mpsc;
+use cosmic::iced::futures::{SinkExt, StreamExt};
+use cosmic::iced::{stream, window, Alignment, Color, Length, Limits, Subscription};
 use cosmic::iced_widget::Row;
 use cosmic::iced_winit::commands::popup::{destroy_popup, get_popup};
 use cosmic::widget::{self, autosize, horizontal_space, vertical_space};
 use cosmic::{Application, Element};
 use tokio::time;
 
-use crate::config::WorldClocksConfig;
-use crate::{editor, tz::ValidTz};
-use anyhow::Result;
+use crate::config::{self, CosmicTimeConfig, Placement, WorldClocksConfig, COSMIC_TIME_APP_ID};
+use crate::editor;
+use crate::fl;
+use crate::tz::{self, Availability, Transition, TzError, ValidTz};
 
 /// This is the struct that represents your application.
 /// It is used to define the data that will be used by your application.
@@ -29,7 +32,14 @@ pub struct YourApp {
     // example_row: bool,
     now: chrono::DateTime<chrono::Utc>,
     // config
-    timezones: Vec<Result<ValidTz>>,
+    config: WorldClocksConfig,
+    /// A problem with the config the user should know about, until they dismiss it.
+    status: Option<String>,
+    cosmic_time: CosmicTimeConfig,
+    timezones: Vec<Result<ValidTz, TzError>>,
+    /// Offset changes near `now` for the popup clocks, see [`transition_note`].
+    /// Kept up to date on each tick, finding them is too slow to do on every render.
+    transition_notes: Vec<String>,
 }
 
 static AUTOSIZE_MAIN_ID: LazyLock<widget::Id> = LazyLock::new(|| widget::Id::new("autosize-main"));
@@ -43,6 +53,11 @@ pub enum Message {
     // ToggleExampleRow(bool),
     Tick,
     ConfigChanged(WorldClocksConfig),
+    CosmicTimeChanged(CosmicTimeConfig),
+    TzDataChanged,
+    /// The zone catalog finished (re)loading in the background.
+    CatalogLoaded,
+    DismissStatus,
     Editor(editor::Message),
 }
 
@@ -52,14 +67,159 @@ impl From<editor::Message> for Message {
     }
 }
 
+/// e.g. "London moves to BST in 3 days (+1h)", for changes coming up soon or only just gone.
+fn transition_note(tz: &ValidTz, now: chrono::DateTime<chrono::Utc>) -> Option<String> {
+    let upcoming = tz
+        .next_transition(now)
+        .filter(|t| t.at - now <= chrono::TimeDelta::days(30));
+    if let Some(t) = upcoming {
+        return Some(fl!(
+            "transition-upcoming",
+            name = tz.display_name.as_str(),
+            abbreviation = t.abbreviation_after.as_str(),
+            time = humanize(t.at - now),
+            change = offset_change(&t)
+        ));
+    }
+    let recent = tz
+        .previous_transition(now)
+        .filter(|t| now - t.at <= chrono::TimeDelta::days(7))?;
+    Some(fl!(
+        "transition-recent",
+        name = tz.display_name.as_str(),
+        abbreviation = recent.abbreviation_after.as_str(),
+        time = humanize(now - recent.at),
+        change = offset_change(&recent)
+    ))
+}
+
+fn humanize(d: chrono::TimeDelta) -> String {
+    if d < chrono::TimeDelta::hours(1) {
+        fl!("duration-minutes", count = d.num_minutes().max(1))
+    } else if d < chrono::TimeDelta::hours(48) {
+        fl!("duration-hours", count = d.num_hours())
+    } else {
+        fl!("duration-days", count = d.num_days())
+    }
+}
+
+/// e.g. "+1h", "-30m"
+fn offset_change(t: &Transition) -> String {
+    let diff = t.after.local_minus_utc() - t.before.local_minus_utc();
+    let sign = if diff < 0 { '-' } else { '+' };
+    let (hours, minutes) = (diff.abs() / 3600, diff.abs() % 3600 / 60);
+    match (hours, minutes) {
+        (h, 0) => format!("{}{}h", sign, h),
+        (0, m) => format!("{}{}m", sign, m),
+        (h, m) => format!("{}{}h{}m", sign, h, m),
+    }
+}
+
+/// Builds the zone catalog off the UI thread, so startup doesn't wait on the filesystem.
+fn load_catalog() -> Task<Message> {
+    Task::perform(tz::reload(), |()| {
+        cosmic::Action::App(Message::CatalogLoaded)
+    })
+}
+
 impl YourApp {
-    fn tzs_from_config(c: &WorldClocksConfig) -> Vec<Result<ValidTz>> {
+    fn tzs_from_config(c: &WorldClocksConfig) -> Vec<Result<ValidTz, TzError>> {
         return c
             .timezones
             .iter()
             .map(|tz| ValidTz::from_names(&tz.name, &tz.display_name))
             .collect();
     }
+
+    /// Tell the editor what clocks without a format look like, for its placeholder and preview.
+    fn sync_fallback_format(&mut self) {
+        let format = self.config.fallback_format(&self.cosmic_time);
+        self.editor.set_fallback_format(format);
+    }
+
+    fn set_config(&mut self, mut c: WorldClocksConfig) {
+        // The editor persists this, but don't show errors for old IDs in the meantime.
+        c.migrate_zone_ids();
+        self.timezones = YourApp::tzs_from_config(&c);
+        self.config = c;
+        self.sync_fallback_format();
+        self.update_transition_notes();
+    }
+
+    fn update_transition_notes(&mut self) {
+        self.transition_notes = self
+            .placed(Placement::in_popup)
+            .filter_map(|(_, tz)| tz.as_ref().ok())
+            .filter_map(|tz| transition_note(tz, self.now))
+            .collect();
+    }
+
+    /// Whether any shown clock has seconds, and so needs a tick every second.
+    fn shows_seconds(&self) -> bool {
+        self.placed(Placement::in_popup)
+            .any(|(tz, _)| tz::format_has_seconds(self.config.format_for(tz, &self.cosmic_time)))
+    }
+
+    /// The clocks placed where `shown` says, with their loaded zones.
+    fn placed(
+        &self,
+        shown: impl Fn(Placement) -> bool,
+    ) -> impl Iterator<Item = (&config::Tz, &Result<ValidTz, TzError>)> {
+        self.config
+            .timezones
+            .iter()
+            .zip(&self.timezones)
+            .filter(move |(c, _)| shown(c.placement))
+    }
+
+    /// Puts a clock's flag and icon, if it has them, before `time`.
+    fn decorate<'a>(
+        &self,
+        c: &config::Tz,
+        catalog: Option<&tz::Catalog>,
+        flag_text: impl Fn(String) -> Element<'a, Message>,
+        time: Element<'a, Message>,
+    ) -> Element<'a, Message> {
+        let icon_size = self.core.applet.suggested_size(true).0;
+        let mut row = widget::row::with_capacity(3)
+            .spacing(self.core.applet.suggested_padding(true) / 2)
+            .align_y(Alignment::Center);
+        if let Some(flag) = catalog.and_then(|catalog| c.flag(catalog)) {
+            row = row.push(flag_text(flag));
+        }
+        if let Some(icon) = &c.icon {
+            row = row.push(
+                widget::icon::from_name(icon.as_str())
+                    .symbolic(true)
+                    .size(icon_size)
+                    .icon(),
+            );
+        }
+        row.push(time).into()
+    }
+}
+
+/// The clock's color, if it has one, faded out of working hours.
+fn time_class(
+    c: &config::Tz,
+    tz: &ValidTz,
+    now: chrono::DateTime<chrono::Utc>,
+) -> cosmic::theme::Text {
+    let alpha = match c
+        .working_hours
+        .as_ref()
+        .map(|hours| tz.availability(now, hours))
+    {
+        None | Some(Availability::Working) => 1.0,
+        Some(Availability::OffHours) => 0.6,
+        Some(Availability::Asleep) => 0.35,
+    };
+    let color = match c.rgb() {
+        Some([r, g, b]) => Color::from_rgb8(r, g, b),
+        None if alpha < 1.0 => Color::from(cosmic::theme::active().cosmic().on_bg_color()),
+        None => return cosmic::theme::Text::Default,
+    };
+    cosmic::theme::Text::Color(Color { a: alpha, ..color })
 }
 
 /// Implement the `Application` trait for your application.
@@ -95,28 +255,48 @@ impl Application for YourApp {
     /// - `flags` is used to pass in any data that your application needs to use before it starts.
     /// - `Command` type is used to send messages to your application. `Command::none()` can be used to send no messages to your application.
     fn init(core: Core, _flags: Self::Flags) -> (Self, Task<Self::Message>) {
-        // TODO: try to see if local config doesn't exist yet, if so write the default.
+        crate::config::migrate(Self::APP_ID);
+
+        let handle = cosmic_config::Config::new(Self::APP_ID, WorldClocksConfig::VERSION);
+        let (config, status) = match &handle {
+            Ok(handle) => WorldClocksConfig::load_or_seed(handle),
+            Err(err) => {
+                tracing::error!(?err, "Error opening config");
+                let status = fl!("config-read-error", reason = err.to_string());
+                (WorldClocksConfig::default(), Some(status))
+            }
+        };
 
-        // let mut config = match WorldClocksConfig::get_entry(&cconfig) {
-        //     Ok(config) => config,
-        //     Err((e, config)) => {
-        //         println!("{:#?}", e); // TODO!!
-        //         config
-        //     }
-        // };
-        let config = WorldClocksConfig::default();
+        // The zoneinfo root is fixed for the life of the process, so set it before loading zones.
+        tz::configure_root(config.zoneinfo_root.clone());
 
         let timezones = YourApp::tzs_from_config(&config);
 
-        let app = YourApp {
+        let cosmic_time = cosmic_config::Config::new(COSMIC_TIME_APP_ID, CosmicTimeConfig::VERSION)
+            .map(|c| match CosmicTimeConfig::get_entry(&c) {
+                Ok(t) => t,
+                Err((errs, t)) => {
+                    tracing::warn!(?errs, "Error reading COSMIC time applet config");
+                    t
+                }
+            })
+            .unwrap_or_default();
+
+        let mut app = YourApp {
             core,
             now: chrono::Utc::now(),
+            editor: editor::Editor::new(handle.ok(), config.clone()), // ..Default::default()
+            config,
+            status,
+            cosmic_time,
             timezones: timezones,
+            transition_notes: Vec::new(),
             popup: None,
-            editor: editor::Editor::new(YourApp::APP_ID), // ..Default::default()
         };
+        app.sync_fallback_format();
+        app.update_transition_notes();
 
-        (app, Task::none())
+        (app, load_catalog())
     }
 
     fn on_close_requested(&self, id: window::Id) -> Option<Message> {
@@ -130,20 +310,51 @@ impl Application for YourApp {
     ///
     /// To get a better sense of which widgets are available, check out the `widget` module.
     fn view(&self) -> Element<Self::Message> {
-        let texts = self.timezones.iter().map(|rtz| {
+        let catalog = tz::catalog();
+        let texts = self.placed(Placement::in_panel).map(|(c, rtz)| {
             let Ok(tz) = rtz else {
-                return Element::from(self.core.applet.text("Error!"));
+                return Element::from(self.core.applet.text(fl!("clock-error")));
+            };
+            let time_str = tz.format(self.now, self.config.format_for(c, &self.cosmic_time));
+            let s = if self.config.show_abbreviation {
+                format!(
+                    "{} {} {}",
+                    time_str,
+                    tz.abbreviation(self.now),
+                    tz.display_name
+                )
+            } else {
+                format!("{} {}", time_str, tz.display_name)
             };
-            let time_str = self.now.with_timezone(&&tz.tz).format("%H:%M");
-            let s = format!("{} {}", time_str, tz.display_name);
-            Element::from(self.core.applet.text(s))
+            let flag_text = |flag: String| self.core.applet.text(flag).into();
+            let time = self.core.applet.text(s).class(time_class(c, tz, self.now));
+            self.decorate(c, catalog.as_deref(), flag_text, time.into())
         });
+        // Keep something to click on when every clock is popup-only.
+        let mut texts = texts.peekable();
+        let texts = if texts.peek().is_some() {
+            itertools::Either::Left(texts)
+        } else {
+            itertools::Either::Right(std::iter::once(Element::from(
+                widget::icon::from_name("preferences-system-time-symbolic")
+                    .size(self.core.applet.suggested_size(true).0)
+                    .icon(),
+            )))
+        };
 
         let pad = Length::Fixed(self.core.applet.suggested_padding(true).into());
         let height =
             self.core.applet.suggested_size(true).1 + 2 * self.core.applet.suggested_padding(true);
         let vspacer = vertical_space().height(Length::Fixed(height.into()));
 
+        let warning = self.status.as_ref().map(|_| {
+            Element::from(
+                widget::icon::from_name("dialog-warning-symbolic")
+                    .size(self.core.applet.suggested_size(true).0)
+                    .icon(),
+            )
+        });
+        let texts = warning.into_iter().chain(texts);
         let elems =
             itertools::intersperse_with(texts, || Element::from(horizontal_space().width(pad)));
         let content = Row::from_iter(elems)
@@ -169,10 +380,66 @@ impl Application for YourApp {
         //     ));
         // self.core.applet.popup_container(content_list).into()
 
-        self.core
-            .applet
-            .popup_container(self.editor.view().map(Message::Editor))
-            .into()
+        let catalog = tz::catalog();
+        let clocks = self.placed(Placement::in_popup).map(|(c, tz)| {
+            let time = match tz {
+                Ok(tz) => {
+                    let format = self.config.format_for(c, &self.cosmic_time);
+                    let time_str = tz.format(self.now, format);
+                    let time = format!("{} {}", time_str, tz.abbreviation(self.now));
+                    let time = widget::text::body(time).class(time_class(c, tz, self.now));
+                    let flag_text = |flag: String| widget::text::body(flag).into();
+                    self.decorate(c, catalog.as_deref(), flag_text, time.into())
+                }
+                Err(err) => widget::text::caption(err.to_string()).into(),
+            };
+            Element::from(widget::row::with_children(vec![
+                widget::text::body(&c.display_name).into(),
+                horizontal_space().into(),
+                time,
+            ]))
+        });
+        let notes = self
+            .transition_notes
+            .iter()
+            .map(|note| Element::from(widget::text::caption(note)));
+        // Quick switching, the editor below also manages profiles.
+        let profiles = (self.config.profiles.len() > 1).then(|| {
+            let names: Vec<String> = self
+                .config
+                .profiles
+                .iter()
+                .map(|p| p.name.clone())
+                .collect();
+            widget::dropdown(
+                &self.config.profiles,
+                names.iter().position(|n| *n == self.config.active_profile),
+                move |i| {
+                    let name = names[i].clone();
+                    Message::Editor(editor::Message::EditProfile(editor::EditProfile::Switch(
+                        name,
+                    )))
+                },
+            )
+        });
+        let status = self.status.as_ref().map(|status| {
+            widget::row::with_children(vec![
+                widget::text::caption(status).width(Length::Fill).into(),
+                widget::button::icon(widget::icon::from_name("window-close-symbolic"))
+                    .extra_small()
+                    .on_press(Message::DismissStatus)
+                    .into(),
+            ])
+            .align_y(Alignment::Center)
+        });
+        let content = widget::column()
+            .push_maybe(status)
+            .push_maybe(profiles)
+            .extend(clocks.chain(notes))
+            .padding([0, 5])
+            .push(self.editor.view().map(Message::Editor));
+
+        self.core.applet.popup_container(content).into()
     }
 
     /// Application messages are handled here. The application state can be modified based on
@@ -206,13 +473,35 @@ impl Application for YourApp {
                     self.popup = None;
                 }
             }
-            Message::ConfigChanged(c) => self.timezones = YourApp::tzs_from_config(&c),
+            Message::ConfigChanged(c) => {
+                self.set_config(c);
+            }
+            Message::CosmicTimeChanged(t) => {
+                self.cosmic_time = t;
+                self.sync_fallback_format();
+            }
+            Message::TzDataChanged => {
+                tracing::info!("tzdata changed, reloading zones");
+                return load_catalog();
+            }
+            Message::CatalogLoaded => {
+                // Now we know about renames, and have suggestions for any broken zones.
+                self.config.migrate_zone_ids();
+                self.timezones = YourApp::tzs_from_config(&self.config);
+                self.editor.reload_zones();
+                self.update_transition_notes();
+            }
             Message::Tick => {
                 self.now = chrono::Utc::now();
+                self.update_transition_notes();
+            }
+            Message::DismissStatus => {
+                self.status = None;
             }
             Message::Editor(msg) => {
                 match self.editor.update(msg) {
                     None => {}
+                    Some(editor::Output::NewConfig(c)) => self.set_config(c),
                 };
             }
         }
@@ -224,22 +513,27 @@ impl Application for YourApp {
     }
 
     fn subscription(&self) -> cosmic::iced::Subscription<Self::Message> {
-        fn time_subscription() -> Subscription<Message> {
+        /// Ticks on the minute, or on the second when `seconds` is set.
+        /// The id includes `seconds`, so changing it swaps in a new subscription.
+        fn time_subscription(seconds: bool) -> Subscription<Message> {
+            let resolution = if seconds {
+                chrono::TimeDelta::seconds(1)
+            } else {
+                chrono::TimeDelta::minutes(1)
+            };
             Subscription::run_with_id(
-                "time_sub",
+                ("time_sub", seconds),
                 stream::channel(1, async move |mut output| {
-                    let mut timer = time::interval(time::Duration::from_secs(60));
+                    let mut timer = time::interval(resolution.to_std().unwrap());
 
                     loop {
                         timer.tick().await;
                         let _ = output.send(Message::Tick).await;
 
                         let now = chrono::Utc::now();
-                        let next_minute_dt = now
-                            .duration_round_up(chrono::TimeDelta::minutes(1))
-                            .unwrap();
+                        let next_tick_dt = now.duration_round_up(resolution).unwrap();
                         let diff = time::Duration::from_millis(
-                            (next_minute_dt - now).num_milliseconds().max(0) as u64,
+                            (next_tick_dt - now).num_milliseconds().max(0) as u64,
                         );
                         timer.reset_after(diff);
                     }
@@ -247,13 +541,43 @@ impl Application for YourApp {
             )
         }
 
+        fn tzdata_subscription() -> Subscription<Message> {
+            Subscription::run_with_id(
+                "tzdata_sub",
+                stream::channel(1, async move |mut output| {
+                    let (tx, mut rx) = mpsc::unbounded();
+                    // Keep the watcher alive as long as we are, if there's nothing to watch
+                    // tx is dropped and we finish straight away.
+                    let _watcher = tz::watch(move || {
+                        let _ = tx.unbounded_send(());
+                    });
+
+                    while rx.next().await.is_some() {
+                        // A tzdata upgrade touches hundreds of files, let it finish first.
+                        time::sleep(time::Duration::from_secs(2)).await;
+                        while let Ok(Some(())) = rx.try_next() {}
+                        let _ = output.send(Message::TzDataChanged).await;
+                    }
+                }),
+            )
+        }
+
         let config_subscription = self.core.watch_config(Self::APP_ID).map(|u| {
             for err in u.errors {
                 tracing::error!(?err, "Error watching config");
             }
             Message::ConfigChanged(u.config)
         });
-
-        Subscription::batch(vec![time_subscription(), config_subscription])
+        let cosmic_time_subscription = self
+            .core
+            .watch_config(COSMIC_TIME_APP_ID)
+            .map(|u| Message::CosmicTimeChanged(u.config));
+
+        Subscription::batch(vec![
+            time_subscription(self.shows_seconds()),
+            tzdata_subscription(),
+            config_subscription,
+            cosmic_time_subscription,
+        ])
     }
 }
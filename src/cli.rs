@@ -0,0 +1,58 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Importing and exporting clock lists without opening the panel.
+
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use cosmic::cosmic_config::{self, CosmicConfigEntry};
+use cosmic::Application;
+
+use crate::app::YourApp;
+use crate::config::{self, ImportMode, WorldClocksConfig};
+use crate::tz;
+
+const USAGE: &str = "\
+Usage: world-clocks                              run the applet
+       world-clocks export FILE                  save the clock list
+       world-clocks import FILE --merge|--replace
+FILE is JSON if it ends in .json, TOML otherwise.";
+
+/// Runs a command and returns the exit code.
+pub fn run(args: &[String]) -> i32 {
+    match command(args) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("{:#}", err);
+            1
+        }
+    }
+}
+
+fn command(args: &[String]) -> Result<()> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    config::migrate(YourApp::APP_ID);
+    let app_config = cosmic_config::Config::new(YourApp::APP_ID, WorldClocksConfig::VERSION)?;
+    let mut current = WorldClocksConfig::load(&app_config);
+
+    match args.as_slice() {
+        ["export", path] => {
+            config::export(&current, Path::new(path))?;
+            println!("Exported {} clocks to {}", current.timezones.len(), path);
+        }
+        ["import", path, mode @ ("--merge" | "--replace")] => {
+            // Checking the zones needs the same tz database the applet uses.
+            tz::configure_root(current.zoneinfo_root.clone());
+            let mode = match *mode {
+                "--merge" => ImportMode::Merge,
+                _ => ImportMode::Replace,
+            };
+            let added = config::import(Path::new(path))?.apply(&mut current, mode);
+            current.write_entry(&app_config)?;
+            println!("Imported {} clocks from {}", added, path);
+        }
+        ["--help" | "-h"] => println!("{}", USAGE),
+        _ => bail!(USAGE),
+    }
+    Ok(())
+}
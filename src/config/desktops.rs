@@ -0,0 +1,167 @@
+//! Bringing world clocks over from GNOME Clocks and KDE's digital clock.
+
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use anyhow::{bail, Context, Result};
+use itertools::Itertools;
+use regex::Regex;
+
+use super::Tz;
+use crate::tz::{self, Catalog, Coordinates, ValidTz};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Desktop {
+    Gnome,
+    Kde,
+}
+
+/// A clock from another desktop, shown for review before it's added.
+#[derive(Debug, Clone)]
+pub struct FoundClock {
+    /// What the other desktop called it, e.g. "Sydney" or "Asia/Tokyo".
+    pub source: String,
+    /// The clock to add, or why it can't be.
+    pub clock: Result<Tz, String>,
+}
+
+/// A GWeather location in GNOME Clocks' `world-clocks` setting, e.g.
+/// `<('Sydney', 'YSSY', true, [(-0.5925, 2.6386)], [...])>`, with coordinates in radians.
+/// Strings containing `'` are double-quoted instead.
+static GNOME_LOCATION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r#"<\(\s*(?:'((?:[^'\\]|\\.)*)'|"((?:[^"\\]|\\.)*)")\s*,"#,
+        r#"\s*(?:'(?:[^'\\]|\\.)*'|"(?:[^"\\]|\\.)*")\s*,\s*(?:true|false)\s*,"#,
+        r#"\s*(?:@a\(dd\)\s*)?\[\s*(?:\(\s*([-+0-9.eE]+)\s*,\s*([-+0-9.eE]+)\s*\))?"#,
+    ))
+    .unwrap()
+});
+static GVARIANT_ESCAPE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\\(.)").unwrap());
+
+/// Reads a desktop's clocks from `path`, or from wherever that desktop keeps them.
+pub fn find_clocks(
+    desktop: Desktop,
+    path: Option<&Path>,
+    catalog: &Catalog,
+) -> Result<Vec<FoundClock>> {
+    let settings = match (path, desktop) {
+        (Some(path), _) => std::fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read {}", path.display()))?,
+        (None, Desktop::Gnome) => gnome_settings()?,
+        (None, Desktop::Kde) => kde_settings()?,
+    };
+    let found = match desktop {
+        Desktop::Gnome => from_gnome(&settings, catalog),
+        Desktop::Kde => from_kde(&settings),
+    };
+    if found.is_empty() {
+        bail!("No world clocks found");
+    }
+    Ok(found)
+}
+
+/// Accepts `gsettings get` output, a `dconf dump` or the Flatpak keyfile, as they all print
+/// the value the same way. GNOME stores places rather than zones, so we look the name up in
+/// our city list, or failing that take the zone nearest to it.
+fn from_gnome(settings: &str, catalog: &Catalog) -> Vec<FoundClock> {
+    GNOME_LOCATION
+        .captures_iter(settings)
+        .map(|caps| {
+            let quoted = caps.get(1).or(caps.get(2)).map_or("", |m| m.as_str());
+            let name = GVARIANT_ESCAPE.replace_all(quoted, "$1").into_owned();
+            let coordinates = match (caps.get(3), caps.get(4)) {
+                (Some(lat), Some(lon)) => lat
+                    .as_str()
+                    .parse::<f64>()
+                    .ok()
+                    .zip(lon.as_str().parse::<f64>().ok())
+                    .map(|(lat, lon)| Coordinates {
+                        latitude: lat.to_degrees(),
+                        longitude: lon.to_degrees(),
+                    }),
+                _ => None,
+            };
+            let zone = catalog
+                .find_city(&name)
+                .map(|c| catalog.canonical_name(&c.zone).to_owned())
+                .or_else(|| Some(catalog.nearest_zone(coordinates?)?.name.clone()));
+            let clock = match zone {
+                Some(zone) => check(Tz {
+                    name: zone,
+                    display_name: name.clone(),
+                    ..Default::default()
+                }),
+                None => Err(format!("No timezone found for {}", name)),
+            };
+            FoundClock {
+                source: name,
+                clock,
+            }
+        })
+        .collect()
+}
+
+/// Every digital clock's `selectedTimeZones=` in plasma's applet config,
+/// e.g. `Local,Europe/London,Asia/Tokyo`.
+fn from_kde(settings: &str) -> Vec<FoundClock> {
+    settings
+        .lines()
+        .filter_map(|l| l.trim().strip_prefix("selectedTimeZones="))
+        .flat_map(|zones| zones.split(','))
+        .map(str::trim)
+        // Local is the system zone, which the panel's own clock already shows.
+        .filter(|name| !name.is_empty() && *name != "Local")
+        .unique()
+        .map(|name| FoundClock {
+            source: name.to_owned(),
+            clock: check(Tz {
+                name: name.to_owned(),
+                display_name: tz::default_display_name(name),
+                ..Default::default()
+            }),
+        })
+        .collect()
+}
+
+fn check(tz: Tz) -> Result<Tz, String> {
+    match ValidTz::from_names(&tz.name, &tz.display_name) {
+        Ok(_) => Ok(tz),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+fn home() -> Result<PathBuf> {
+    std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .context("HOME isn't set")
+}
+
+/// The Flatpak keeps settings in a keyfile, otherwise they're in dconf and we ask `gsettings`.
+fn gnome_settings() -> Result<String> {
+    let keyfile = home()?.join(".var/app/org.gnome.clocks/config/glib-2.0/settings/keyfile");
+    if let Ok(s) = std::fs::read_to_string(&keyfile) {
+        if s.contains("world-clocks") {
+            return Ok(s);
+        }
+    }
+    let output = std::process::Command::new("gsettings")
+        .args(["get", "org.gnome.clocks", "world-clocks"])
+        .output()
+        .context("Couldn't run gsettings, is GNOME Clocks installed?")?;
+    if !output.status.success() {
+        bail!(
+            "gsettings: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn kde_settings() -> Result<String> {
+    let config_dir = match std::env::var_os("XDG_CONFIG_HOME").filter(|d| !d.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => home()?.join(".config"),
+    };
+    let path = config_dir.join("plasma-org.kde.plasma.desktop-appletsrc");
+    std::fs::read_to_string(&path).with_context(|| format!("Couldn't read {}", path.display()))
+}
@@ -0,0 +1,150 @@
+use cosmic::cosmic_config::{self, ConfigGet, ConfigSet, CosmicConfigEntry};
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{Tz, WorldClocksConfig};
+
+/// Reads the entry for `from` and writes it out as `from + 1`.
+type Step = fn(&cosmic_config::Config, &cosmic_config::Config) -> Result<(), cosmic_config::Error>;
+
+struct Migration {
+    from: u64,
+    migrate: Step,
+}
+
+/// One step per schema bump, in order.
+static MIGRATIONS: &[Migration] = &[Migration {
+    from: 1,
+    migrate: v1_to_v2,
+}];
+
+/// Brings the newest older config up to [`WorldClocksConfig::VERSION`], if the current
+/// version hasn't been written yet. Call before anything reads the config.
+///
+/// cosmic-config keeps each version in its own directory, so the old entry is left as it was.
+/// Each step also keeps a copy of the old clocks in the new entry, under `backup_v{from}`.
+pub fn migrate(app_id: &str) {
+    let version = WorldClocksConfig::VERSION;
+    let has_entry = |v: u64| {
+        cosmic_config::Config::new(app_id, v)
+            .is_ok_and(|c| c.get::<serde::de::IgnoredAny>("timezones").is_ok())
+    };
+    if has_entry(version) {
+        return;
+    }
+    let Some(from) = (1..version).rev().find(|v| has_entry(*v)) else {
+        return;
+    };
+
+    for step in MIGRATIONS.iter().filter(|m| m.from >= from) {
+        let to = step.from + 1;
+        let res = cosmic_config::Config::new(app_id, step.from).and_then(|old| {
+            let new = cosmic_config::Config::new(app_id, to)?;
+            (step.migrate)(&old, &new)
+        });
+        match res {
+            Ok(()) => tracing::info!(from = step.from, to, "Migrated config"),
+            Err(err) => {
+                // Stop here, the old entry is untouched so we can try again next start.
+                tracing::error!(from = step.from, to, ?err, "Error migrating config");
+                return;
+            }
+        }
+    }
+}
+
+/// Version 2 is where migrations start, the clocks themselves didn't change shape. They're read
+/// as today's [`Tz`], so per-clock formats carry over and newer fields take their defaults.
+fn v1_to_v2(
+    old: &cosmic_config::Config,
+    new: &cosmic_config::Config,
+) -> Result<(), cosmic_config::Error> {
+    let timezones: Vec<Tz> = old.get("timezones")?;
+    new.set("backup_v1", &timezones)?;
+    new.set("timezones", timezones)?;
+
+    copy::<Option<String>>(old, new, "zoneinfo_root")?;
+    copy::<bool>(old, new, "show_abbreviation")?;
+    copy::<Option<String>>(old, new, "default_format")?;
+    copy::<Option<bool>>(old, new, "military_time")?;
+    copy::<Option<bool>>(old, new, "show_seconds")
+}
+
+/// Carries a key over unchanged. Missing or unreadable keys are left to default.
+fn copy<T: DeserializeOwned + Serialize>(
+    old: &cosmic_config::Config,
+    new: &cosmic_config::Config,
+    key: &str,
+) -> Result<(), cosmic_config::Error> {
+    match old.get::<T>(key) {
+        Ok(value) => new.set(key, value),
+        Err(err) => {
+            tracing::debug!(key, ?err, "Not migrating config key");
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+
+    use super::*;
+
+    const APP_ID: &str = "it.jmwh.WorldClocks.MigrateTest";
+
+    /// `XDG_CONFIG_HOME` is process-wide, so tests that move it take turns.
+    static CONFIG_HOME_LOCK: Mutex<()> = Mutex::new(());
+
+    /// Points `XDG_CONFIG_HOME` at a scratch dir, putting it back and removing the dir on drop,
+    /// even if the test fails.
+    struct ScratchConfigHome {
+        dir: PathBuf,
+        previous: Option<OsString>,
+    }
+
+    impl ScratchConfigHome {
+        fn new(test: &str) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("world-clocks-{}-{}", test, std::process::id()));
+            let previous = std::env::var_os("XDG_CONFIG_HOME");
+            std::env::set_var("XDG_CONFIG_HOME", &dir);
+            ScratchConfigHome { dir, previous }
+        }
+    }
+
+    impl Drop for ScratchConfigHome {
+        fn drop(&mut self) {
+            match &self.previous {
+                Some(previous) => std::env::set_var("XDG_CONFIG_HOME", previous),
+                None => std::env::remove_var("XDG_CONFIG_HOME"),
+            }
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn v1_to_v2_keeps_per_clock_formats() {
+        let _lock = CONFIG_HOME_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let _home = ScratchConfigHome::new("migrate");
+
+        let v1 = cosmic_config::Config::new(APP_ID, 1).unwrap();
+        let tokyo = Tz {
+            name: "Asia/Tokyo".into(),
+            display_name: "Tokyo".into(),
+            format: Some("%a %H:%M".into()),
+            ..Default::default()
+        };
+        v1.set("timezones", vec![tokyo.clone()]).unwrap();
+        v1.set("default_format", Some("%H:%M")).unwrap();
+
+        migrate(APP_ID);
+
+        let v2 = cosmic_config::Config::new(APP_ID, 2).unwrap();
+        assert_eq!(v2.get::<Vec<Tz>>("timezones").unwrap(), vec![tokyo.clone()]);
+        assert_eq!(v2.get::<Vec<Tz>>("backup_v1").unwrap(), vec![tokyo]);
+        let default_format: Option<String> = v2.get("default_format").unwrap();
+        assert_eq!(default_format.as_deref(), Some("%H:%M"));
+    }
+}
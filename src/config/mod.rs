@@ -0,0 +1,251 @@
+use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::fl;
+use crate::tz;
+
+mod desktops;
+mod migrate;
+mod portable;
+mod profiles;
+pub use desktops::{find_clocks, Desktop, FoundClock};
+pub use migrate::migrate;
+pub use portable::{export, import, ImportMode};
+pub use profiles::{Profile, DEFAULT_PROFILE};
+
+/// Bump the version whenever a field changes shape, and add a step to [`migrate`].
+#[derive(Debug, Clone, CosmicConfigEntry, PartialEq, Eq)]
+#[version = 2]
+pub struct WorldClocksConfig {
+    /// The active profile's clocks.
+    pub timezones: Vec<Tz>,
+    pub active_profile: String,
+    /// Every clock list by name. The active one's clocks are in `timezones` instead.
+    pub profiles: Vec<Profile>,
+    /// Overrides `TZDIR` and the default search paths for the tz database.
+    pub zoneinfo_root: Option<String>,
+    /// Show e.g. "AEST" next to each time in the panel.
+    pub show_abbreviation: bool,
+    /// strftime-style format for clocks that don't set their own, e.g. `%H:%M`.
+    pub default_format: Option<String>,
+    /// 24-hour time, `None` to follow the COSMIC time applet.
+    pub military_time: Option<bool>,
+    /// `None` to follow the COSMIC time applet.
+    pub show_seconds: Option<bool>,
+}
+
+/// The COSMIC time applet's settings, which we follow unless overridden.
+/// Only the fields we care about, the applet has more.
+#[derive(Debug, Clone, Default, CosmicConfigEntry, PartialEq, Eq)]
+#[version = 1]
+pub struct CosmicTimeConfig {
+    pub military_time: bool,
+    pub show_seconds: bool,
+}
+
+pub const COSMIC_TIME_APP_ID: &str = "com.system76.CosmicAppletTime";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Tz {
+    pub name: String,
+    pub display_name: String,
+    /// Overrides [`WorldClocksConfig::default_format`] for this clock, e.g. `%a %H:%M`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// Colors the time, `#rrggbb` or `#rgb`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// A symbolic icon shown before the time, e.g. `starred-symbolic`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    /// Show the flag of the zone's country before the time.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub show_flag: bool,
+    #[serde(default, skip_serializing_if = "Placement::is_default")]
+    pub placement: Placement,
+    /// Dims the clock outside these hours, and more so when people there are asleep.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub working_hours: Option<tz::WorkingHours>,
+}
+
+/// Where a clock is shown. Clocks are always listed in the editor.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Placement {
+    #[default]
+    PanelAndPopup,
+    PopupOnly,
+    Hidden,
+}
+
+impl Placement {
+    pub const ALL: [Placement; 3] = [
+        Placement::PanelAndPopup,
+        Placement::PopupOnly,
+        Placement::Hidden,
+    ];
+
+    fn is_default(&self) -> bool {
+        *self == Placement::default()
+    }
+
+    pub fn in_panel(self) -> bool {
+        self == Placement::PanelAndPopup
+    }
+
+    pub fn in_popup(self) -> bool {
+        self != Placement::Hidden
+    }
+}
+
+impl Default for WorldClocksConfig {
+    fn default() -> Self {
+        Self {
+            timezones: vec![
+                Tz {
+                    name: "Etc/UTC".into(),
+                    display_name: "UTC".into(),
+                    ..Default::default()
+                },
+                Tz {
+                    name: "Europe/London".into(),
+                    display_name: "London".into(),
+                    ..Default::default()
+                },
+                Tz {
+                    name: "Australia/Perth".into(),
+                    display_name: "Perth".into(),
+                    ..Default::default()
+                },
+            ],
+            active_profile: DEFAULT_PROFILE.to_owned(),
+            profiles: Vec::new(),
+            zoneinfo_root: None,
+            show_abbreviation: false,
+            default_format: None,
+            military_time: None,
+            show_seconds: None,
+        }
+    }
+}
+
+impl WorldClocksConfig {
+    /// Reads the config, keeping whatever did load rather than throwing the user's clocks away.
+    pub fn load(config: &cosmic_config::Config) -> Self {
+        match Self::get_entry(config) {
+            Ok(c) => c,
+            Err((errs, c)) => {
+                tracing::error!(?errs, "Error reading config");
+                c
+            }
+        }
+    }
+
+    /// Reads the config at startup, writing out defaults for anything that isn't there yet,
+    /// e.g. on the first run. Also returns what went wrong, if anything, for the user to see.
+    pub fn load_or_seed(config: &cosmic_config::Config) -> (Self, Option<String>) {
+        match Self::get_entry(config) {
+            Ok(c) => (c, None),
+            Err((errs, c)) if errs.iter().all(is_missing) => {
+                tracing::info!(missing = errs.len(), "Writing default config");
+                match c.write_entry(config) {
+                    Ok(()) => (c, None),
+                    Err(err) => {
+                        tracing::error!(?err, "Error writing default config");
+                        let status = fl!("config-write-error", reason = err.to_string());
+                        (c, Some(status))
+                    }
+                }
+            }
+            Err((errs, c)) => {
+                // Don't seed here, we'd overwrite settings the user may want to fix by hand.
+                tracing::error!(?errs, "Error reading config");
+                let reason = errs.iter().map(ToString::to_string).join("; ");
+                (c, Some(fl!("config-read-error", reason = reason)))
+            }
+        }
+    }
+
+    /// The format a clock's time is shown in: its own, else the configured default,
+    /// else [`Self::fallback_format`].
+    pub fn format_for<'a>(&'a self, tz: &'a Tz, cosmic: &CosmicTimeConfig) -> &'a str {
+        tz.format
+            .as_deref()
+            .or(self.default_format.as_deref())
+            .unwrap_or_else(|| self.fallback_format(cosmic))
+    }
+
+    /// A format matching the 12/24-hour and seconds settings, ours or else COSMIC's.
+    pub fn fallback_format(&self, cosmic: &CosmicTimeConfig) -> &'static str {
+        let military_time = self.military_time.unwrap_or(cosmic.military_time);
+        let show_seconds = self.show_seconds.unwrap_or(cosmic.show_seconds);
+        match (military_time, show_seconds) {
+            (true, false) => tz::DEFAULT_FORMAT,
+            (true, true) => "%H:%M:%S",
+            (false, false) => "%-I:%M %p",
+            (false, true) => "%-I:%M:%S %p",
+        }
+    }
+
+    /// Rewrites renamed zone IDs to their current names in every profile, keeping display names.
+    /// Returns each `(old, new)` pair that was rewritten, nothing if the catalog isn't loaded yet.
+    pub fn migrate_zone_ids(&mut self) -> Vec<(String, String)> {
+        let Some(catalog) = tz::catalog() else {
+            return Vec::new();
+        };
+        let mut renamed: Vec<_> = self
+            .timezones
+            .iter_mut()
+            .filter_map(|tz| tz.migrate_id(&catalog))
+            .collect();
+        renamed.extend(self.migrate_profile_zone_ids(&catalog));
+        renamed
+    }
+}
+
+impl Tz {
+    /// The color as RGB, if it's set and parses.
+    pub fn rgb(&self) -> Option<[u8; 3]> {
+        parse_color(self.color.as_deref()?)
+    }
+
+    /// The country flag to show, if it's turned on and we know the zone's country.
+    pub fn flag(&self, catalog: &tz::Catalog) -> Option<String> {
+        if !self.show_flag {
+            return None;
+        }
+        catalog.flag(&self.name)
+    }
+
+    /// Rewrites a renamed zone ID to its current name, returning `(old, new)` if it was.
+    pub fn migrate_id(&mut self, catalog: &tz::Catalog) -> Option<(String, String)> {
+        let new = catalog.current_name(&self.name)?.to_owned();
+        let old = std::mem::replace(&mut self.name, new.clone());
+        tracing::info!(%old, %new, "Migrated renamed timezone");
+        Some((old, new))
+    }
+}
+
+/// A key that hasn't been written yet, as opposed to one we can't read.
+fn is_missing(err: &cosmic_config::Error) -> bool {
+    match err {
+        cosmic_config::Error::NotFound => true,
+        cosmic_config::Error::GetKey(_, err) => err.kind() == std::io::ErrorKind::NotFound,
+        _ => false,
+    }
+}
+
+/// `#rrggbb` or `#rgb` as RGB.
+pub fn parse_color(s: &str) -> Option<[u8; 3]> {
+    let digits: Vec<u8> = s
+        .strip_prefix('#')?
+        .chars()
+        .map(|c| Some(c.to_digit(16)? as u8))
+        .collect::<Option<_>>()?;
+    match digits[..] {
+        [r, g, b] => Some([r * 17, g * 17, b * 17]),
+        [r1, r2, g1, g2, b1, b2] => Some([r1 * 16 + r2, g1 * 16 + g2, b1 * 16 + b2]),
+        _ => None,
+    }
+}
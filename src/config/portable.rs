@@ -0,0 +1,134 @@
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::{Tz, WorldClocksConfig};
+use crate::tz::{self, ValidTz};
+
+/// A clock list as shared between machines, in TOML or JSON.
+/// Machine-specific settings like the zoneinfo root are left out, and every setting but the
+/// clocks themselves is optional.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ClockList {
+    pub timezones: Vec<Tz>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub show_abbreviation: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_format: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub military_time: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub show_seconds: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    /// Add the file's clocks that we don't already have, keeping our settings.
+    Merge,
+    /// Swap our clocks for the file's, and take any settings it has.
+    Replace,
+}
+
+impl From<&WorldClocksConfig> for ClockList {
+    fn from(c: &WorldClocksConfig) -> Self {
+        ClockList {
+            timezones: c.timezones.clone(),
+            show_abbreviation: Some(c.show_abbreviation),
+            default_format: c.default_format.clone(),
+            military_time: c.military_time,
+            show_seconds: c.show_seconds,
+        }
+    }
+}
+
+impl ClockList {
+    /// Applies an import to `config`, returning how many clocks were added.
+    pub fn apply(self, config: &mut WorldClocksConfig, mode: ImportMode) -> usize {
+        match mode {
+            ImportMode::Merge => {
+                let before = config.timezones.len();
+                for tz in self.timezones {
+                    if !config.timezones.contains(&tz) {
+                        config.timezones.push(tz);
+                    }
+                }
+                config.timezones.len() - before
+            }
+            ImportMode::Replace => {
+                let added = self.timezones.len();
+                config.timezones = self.timezones;
+                if let Some(show) = self.show_abbreviation {
+                    config.show_abbreviation = show;
+                }
+                if self.default_format.is_some() {
+                    config.default_format = self.default_format;
+                }
+                if self.military_time.is_some() {
+                    config.military_time = self.military_time;
+                }
+                if self.show_seconds.is_some() {
+                    config.show_seconds = self.show_seconds;
+                }
+                added
+            }
+        }
+    }
+}
+
+/// JSON for `.json` files, TOML for anything else.
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+}
+
+pub fn export(config: &WorldClocksConfig, path: &Path) -> Result<()> {
+    let list = ClockList::from(config);
+    let s = if is_json(path) {
+        serde_json::to_string_pretty(&list)?
+    } else {
+        toml::to_string_pretty(&list)?
+    };
+    std::fs::write(path, s).with_context(|| format!("Couldn't write {}", path.display()))
+}
+
+/// Reads a clock list, refusing it if any zone or format in it doesn't work here.
+pub fn import(path: &Path) -> Result<ClockList> {
+    let s = std::fs::read_to_string(path)
+        .with_context(|| format!("Couldn't read {}", path.display()))?;
+    let list: ClockList = if is_json(path) {
+        serde_json::from_str(&s).with_context(|| format!("Invalid JSON in {}", path.display()))?
+    } else {
+        toml::from_str(&s).with_context(|| format!("Invalid TOML in {}", path.display()))?
+    };
+
+    let mut problems: Vec<String> = list
+        .timezones
+        .iter()
+        .filter_map(|tz| {
+            let err = ValidTz::from_names(&tz.name, &tz.display_name).err()?;
+            Some(format!("{}: {}", tz.display_name, err))
+        })
+        .collect();
+    let formats = list
+        .timezones
+        .iter()
+        .filter_map(|tz| tz.format.as_deref())
+        .chain(list.default_format.as_deref());
+    problems.extend(
+        formats
+            .filter(|f| !tz::is_valid_format(f))
+            .map(|f| format!("invalid format {}", f)),
+    );
+    problems.extend(
+        list.timezones
+            .iter()
+            .filter_map(|tz| tz.color.as_deref())
+            .filter(|c| super::parse_color(c).is_none())
+            .map(|c| format!("invalid color {}", c)),
+    );
+    if !problems.is_empty() {
+        bail!("Not importing {}: {}", path.display(), problems.join("; "));
+    }
+    Ok(list)
+}
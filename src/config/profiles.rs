@@ -0,0 +1,144 @@
+//! Named clock lists, e.g. "Default", "Travel" and "Client A".
+//!
+//! The active list lives in [`WorldClocksConfig::timezones`], so everything that shows clocks
+//! only ever looks there. Its entry in `profiles` keeps the name and position but no clocks.
+
+use anyhow::{bail, Result};
+use cosmic::cosmic_config::{self, ConfigSet};
+use serde::{Deserialize, Serialize};
+
+use super::{Tz, WorldClocksConfig};
+use crate::tz;
+
+pub const DEFAULT_PROFILE: &str = "Default";
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Profile {
+    pub name: String,
+    /// Empty while this is the active profile.
+    pub timezones: Vec<Tz>,
+}
+
+/// For picking a profile from a dropdown.
+impl AsRef<str> for Profile {
+    fn as_ref(&self) -> &str {
+        &self.name
+    }
+}
+
+impl WorldClocksConfig {
+    /// Every profile name in order, including the active one.
+    pub fn profile_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.profiles.iter().map(|p| p.name.clone()).collect();
+        if !names.contains(&self.active_profile) {
+            names.insert(0, self.active_profile.clone());
+        }
+        names
+    }
+
+    pub fn switch_profile(&mut self, name: &str) -> Result<()> {
+        if name == self.active_profile {
+            return Ok(());
+        }
+        self.ensure_active_profile();
+        let Some(to) = self.profile_index(name) else {
+            bail!("There's no profile called {}", name);
+        };
+        let from = self.profile_index(&self.active_profile).unwrap();
+        self.profiles[from].timezones = std::mem::take(&mut self.timezones);
+        self.timezones = std::mem::take(&mut self.profiles[to].timezones);
+        self.active_profile = name.to_owned();
+        Ok(())
+    }
+
+    /// Adds a profile with `timezones` after the others, and switches to it.
+    pub fn create_profile(&mut self, name: &str, timezones: Vec<Tz>) -> Result<()> {
+        let name = self.check_new_name(name)?;
+        self.ensure_active_profile();
+        self.profiles.push(Profile {
+            name: name.clone(),
+            timezones,
+        });
+        self.switch_profile(&name)
+    }
+
+    pub fn rename_profile(&mut self, old: &str, new: &str) -> Result<()> {
+        let new = self.check_new_name(new)?;
+        self.ensure_active_profile();
+        let Some(i) = self.profile_index(old) else {
+            bail!("There's no profile called {}", old);
+        };
+        if self.active_profile == old {
+            self.active_profile = new.clone();
+        }
+        self.profiles[i].name = new;
+        Ok(())
+    }
+
+    /// Deletes a profile, switching to its neighbour first if it's the active one.
+    pub fn delete_profile(&mut self, name: &str) -> Result<()> {
+        self.ensure_active_profile();
+        let Some(i) = self.profile_index(name) else {
+            bail!("There's no profile called {}", name);
+        };
+        if self.profiles.len() == 1 {
+            bail!("Can't delete the only profile");
+        }
+        if name == self.active_profile {
+            let neighbour = if i == 0 { 1 } else { i - 1 };
+            let neighbour = self.profiles[neighbour].name.clone();
+            self.switch_profile(&neighbour)?;
+        }
+        self.profiles.remove(i);
+        Ok(())
+    }
+
+    /// Writes just the profile keys, so we don't clobber other settings. They go in one
+    /// transaction, so the config watcher never sees one profile's name with another's clocks.
+    pub fn write_profiles(&self, config: &cosmic_config::Config) -> Result<()> {
+        let tx = config.transaction();
+        tx.set("profiles", &self.profiles)?;
+        tx.set("timezones", &self.timezones)?;
+        tx.set("active_profile", &self.active_profile)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Rewrites renamed zone IDs in the profiles that aren't active, see
+    /// [`WorldClocksConfig::migrate_zone_ids`].
+    pub fn migrate_profile_zone_ids(&mut self, catalog: &tz::Catalog) -> Vec<(String, String)> {
+        self.profiles
+            .iter_mut()
+            .flat_map(|p| p.timezones.iter_mut())
+            .filter_map(|tz| tz.migrate_id(catalog))
+            .collect()
+    }
+
+    fn profile_index(&self, name: &str) -> Option<usize> {
+        self.profiles.iter().position(|p| p.name == name)
+    }
+
+    /// Configs from before profiles only have the active list.
+    fn ensure_active_profile(&mut self) {
+        if self.profile_index(&self.active_profile).is_none() {
+            self.profiles.insert(
+                0,
+                Profile {
+                    name: self.active_profile.clone(),
+                    timezones: Vec::new(),
+                },
+            );
+        }
+    }
+
+    fn check_new_name(&self, name: &str) -> Result<String> {
+        let name = name.trim();
+        if name.is_empty() {
+            bail!("Profiles need a name");
+        }
+        if self.profile_names().iter().any(|n| n == name) {
+            bail!("There's already a profile called {}", name);
+        }
+        Ok(name.to_owned())
+    }
+}
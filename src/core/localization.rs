@@ -4,7 +4,8 @@ use std::sync::LazyLock;
 
 use i18n_embed::{
     fluent::{fluent_language_loader, FluentLanguageLoader},
-    LanguageLoader,
+    unic_langid::LanguageIdentifier,
+    DefaultLocalizer, LanguageLoader, Localizer,
 };
 use rust_embed::RustEmbed;
 
@@ -12,7 +13,6 @@ use rust_embed::RustEmbed;
 #[folder = "i18n/"]
 struct Localizations;
 
-#[allow(dead_code)]
 pub static LANGUAGE_LOADER: LazyLock<FluentLanguageLoader> = LazyLock::new(|| {
     let loader: FluentLanguageLoader = fluent_language_loader!();
 
@@ -23,6 +23,14 @@ pub static LANGUAGE_LOADER: LazyLock<FluentLanguageLoader> = LazyLock::new(|| {
     loader
 });
 
+/// Switch to the user's languages, falling back to English for anything untranslated.
+pub fn init(requested_languages: &[LanguageIdentifier]) {
+    let localizer = DefaultLocalizer::new(&*LANGUAGE_LOADER, &Localizations);
+    if let Err(err) = localizer.select(requested_languages) {
+        tracing::error!(?err, "Error loading translations");
+    }
+}
+
 #[macro_export]
 macro_rules! fl {
     ($message_id:literal) => {{
@@ -1,30 +1,75 @@
 use cosmic::{
-    cosmic_config::{self, CosmicConfigEntry},
+    cosmic_config::{self, ConfigSet, CosmicConfigEntry},
     iced::{Alignment, Length},
     widget::{self, combo_box, icon},
     Element,
 };
 
+use chrono::{NaiveTime, Weekday};
+use std::path::PathBuf;
+
 use crate::{
-    config::{Tz, WorldClocksConfig},
-    tz::{ValidTz, TZ_NAMES},
+    config::{self, Desktop, FoundClock, ImportMode, Placement, Tz, WorldClocksConfig},
+    fl,
+    tz::{self, CatalogEntry, TzError, ValidTz, WorkingHours, ZoneInfo, TZ_SOURCE},
 };
-use anyhow::Result;
+use itertools::Itertools;
+use serde::Serialize;
 
 pub struct Editor {
     text_input_buf: String,
-    tz_input_state: widget::combo_box::State<String>,
-    tz_input_buf: Option<String>,
+    /// `None` until the zone catalog has loaded.
+    tz_input_state: Option<widget::combo_box::State<CatalogEntry>>,
+    tz_input_buf: Option<CatalogEntry>,
+    /// What's typed into the timezone picker, which may be an offset like `UTC+05:45`.
+    tz_typed: String,
     text_input_ids: Vec<(widget::Id, widget::Id)>,
     editing_item: Option<(usize, ItemEditState)>,
     tzs: Vec<MaybeTz>,
-    app_config: cosmic_config::Config,
+    /// `None` if it couldn't be opened at startup, which the app tells the user about.
+    app_config: Option<cosmic_config::Config>,
+    notice: Option<String>,
+    show_abbreviation: bool,
+    /// Saved on submit, so half-typed formats don't end up in the panel.
+    default_format_buf: String,
+    /// What clocks get with no format set anywhere, following the 12/24-hour and seconds settings.
+    fallback_format: &'static str,
+    military_time: Option<bool>,
+    show_seconds: Option<bool>,
+    /// Where to import or export the clock list.
+    transfer_path: String,
+    /// Clocks from another desktop, waiting for the user to look them over.
+    found_clocks: Option<Vec<FoundClock>>,
+    profile_names: Vec<String>,
+    active_profile: String,
+    /// A name for a new, duplicated or renamed profile.
+    profile_name_buf: String,
 }
 
+/// Choices for the 12/24-hour and seconds dropdowns, indexes as in [`follow_or`].
+static CLOCK_OPTIONS: &[&str] = &["Follow system", "24-hour", "12-hour"];
+static SECONDS_OPTIONS: &[&str] = &["Follow system", "Show", "Hide"];
+/// In the order of [`Placement::ALL`].
+static PLACEMENT_OPTIONS: &[&str] = &["Panel and popup", "Popup only", "Hidden"];
+static WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
 #[derive(Debug, Clone)]
 pub enum ItemEditState {
     Name,
     DisplayName,
+    Format,
+    Color,
+    Icon,
+    Hours,
+    Sleep,
 }
 
 #[derive(Debug, Clone)]
@@ -45,6 +90,39 @@ pub enum EditList {
 pub enum EditItem {
     SetDisplayName(String),
     SetTz(String),
+    /// Swap a broken zone name for a suggested one, keeping the display name.
+    FixTz(String),
+    /// A zone picked by searching for a city, which also names the clock.
+    SetCity {
+        zone: String,
+        city: String,
+    },
+    /// An empty format means use the default.
+    SetFormat(String),
+    /// `#rrggbb` or `#rgb`, empty for none.
+    SetColor(String),
+    /// A symbolic icon name, empty for none.
+    SetIcon(String),
+    SetShowFlag(bool),
+    SetPlacement(Placement),
+    /// Turns working hours on, starting from 9 to 5 on weekdays, or off.
+    SetWorkingHours(bool),
+    /// `09:00-17:00`.
+    SetHours(String),
+    SetSleep(String),
+    ToggleWorkday(Weekday),
+}
+
+/// Profile management. Renaming and deleting act on the active profile.
+#[derive(Debug, Clone)]
+pub enum EditProfile {
+    Switch(String),
+    /// A new profile with no clocks.
+    Create(String),
+    /// A new profile with the active profile's clocks.
+    Duplicate(String),
+    Rename(String),
+    Delete,
 }
 
 #[derive(Debug, Clone)]
@@ -54,6 +132,22 @@ pub enum Message {
     StartEditing(usize, ItemEditState),
     CancelEditing,
     Input(String),
+    TzInput(String),
+    DismissNotice,
+    SetShowAbbreviation(bool),
+    DefaultFormatInput(String),
+    SetDefaultFormat(String),
+    /// `None` follows the COSMIC time applet.
+    SetMilitaryTime(Option<bool>),
+    SetShowSeconds(Option<bool>),
+    TransferPathInput(String),
+    Import(ImportMode),
+    Export,
+    ImportFrom(Desktop),
+    AddFoundClocks,
+    DiscardFoundClocks,
+    EditProfile(EditProfile),
+    ProfileNameInput(String),
 }
 
 impl From<EditList> for Message {
@@ -69,32 +163,62 @@ impl From<(usize, EditItem)> for Message {
 
 #[derive(Debug, Clone)]
 pub enum Output {
-    // NewConfig(WorldClocksConfig)
+    /// Written by the editor, for the panel to show without waiting for the config watcher.
+    NewConfig(WorldClocksConfig),
 }
-type MaybeTz = Result<Tz, (Tz, anyhow::Error)>;
+type MaybeTz = Result<Tz, (Tz, TzError)>;
 
 impl Editor {
-    pub fn new(app_id: &str) -> Self {
-        let app_config = cosmic_config::Config::new(app_id, 1).unwrap();
-        let initial_config = WorldClocksConfig::get_entry(&app_config).unwrap_or_default();
-        let tzs: Vec<MaybeTz> = initial_config.timezones.into_iter().map(validate).collect();
-        let n = tzs.len();
-        return Editor {
+    pub fn new(
+        app_config: Option<cosmic_config::Config>,
+        initial_config: WorldClocksConfig,
+    ) -> Self {
+        let mut editor = Editor {
             editing_item: None,
-            tzs: tzs,
-            text_input_ids: (0..n)
-                .map(|i| {
-                    (
-                        widget::Id::new(format!("input-full-{i}")),
-                        widget::Id::new(format!("input-display-{i}")),
-                    )
-                })
-                .collect(),
+            tzs: Vec::new(),
+            text_input_ids: Vec::new(),
             text_input_buf: String::new(),
-            tz_input_state: combo_box::State::new(TZ_NAMES.to_vec()),
+            tz_input_state: None,
             tz_input_buf: None,
+            tz_typed: String::new(),
             app_config,
+            notice: None,
+            show_abbreviation: false,
+            default_format_buf: String::new(),
+            fallback_format: tz::DEFAULT_FORMAT,
+            military_time: None,
+            show_seconds: None,
+            transfer_path: String::new(),
+            found_clocks: None,
+            profile_names: Vec::new(),
+            active_profile: String::new(),
+            profile_name_buf: String::new(),
         };
+        editor.load_settings(initial_config);
+        return editor;
+    }
+
+    /// Shows a whole new config, e.g. at startup or after an import.
+    fn load_settings(&mut self, c: WorldClocksConfig) {
+        self.profile_names = c.profile_names();
+        self.active_profile = c.active_profile;
+        self.set_tzs(c.timezones);
+        self.show_abbreviation = c.show_abbreviation;
+        self.default_format_buf = c.default_format.unwrap_or_default();
+        self.military_time = c.military_time;
+        self.show_seconds = c.show_seconds;
+    }
+
+    fn set_tzs(&mut self, tzs: Vec<Tz>) {
+        self.tzs = tzs.into_iter().map(validate).collect();
+        self.text_input_ids = (0..self.tzs.len())
+            .map(|i| {
+                (
+                    widget::Id::new(format!("input-full-{i}")),
+                    widget::Id::new(format!("input-display-{i}")),
+                )
+            })
+            .collect();
     }
     pub(super) fn view(&self) -> cosmic::Element<Message> {
         // for each tz, draw a row with
@@ -104,20 +228,38 @@ impl Editor {
         let cosmic::cosmic_theme::Spacing { space_xs, .. } =
             cosmic::theme::active().cosmic().spacing;
 
-        let mut content_list = widget::list_column().padding(5).spacing(0).add(
-            widget::row::with_children(vec![
-                widget::button::icon(icon::from_name("list-add-symbolic"))
-                    .label("Add")
-                    .on_press(EditList::AddAtEnd().into())
-                    .into(),
-                widget::horizontal_space().into(),
-                widget::button::icon(icon::from_name("document-revert-symbolic"))
-                    .label("Reset")
-                    .on_press(EditList::NewConfig(WorldClocksConfig::default()).into())
-                    .into(),
-            ])
-            .align_y(Alignment::Center),
-        );
+        let mut content_list = widget::list_column()
+            .padding(5)
+            .spacing(0)
+            .add(self.profiles_view(space_xs))
+            .add(
+                widget::row::with_children(vec![
+                    widget::button::icon(icon::from_name("list-add-symbolic"))
+                        .label("Add")
+                        .on_press(EditList::AddAtEnd().into())
+                        .into(),
+                    widget::horizontal_space().into(),
+                    widget::button::icon(icon::from_name("document-revert-symbolic"))
+                        .label("Reset")
+                        .on_press(EditList::NewConfig(WorldClocksConfig::default()).into())
+                        .into(),
+                ])
+                .align_y(Alignment::Center),
+            );
+
+        if let Some(notice) = &self.notice {
+            content_list = content_list.add(
+                widget::row::with_children(vec![
+                    widget::text::caption(notice).width(Length::Fill).into(),
+                    widget::button::icon(icon::from_name("window-close-symbolic"))
+                        .extra_small()
+                        .on_press(Message::DismissNotice)
+                        .into(),
+                ])
+                .spacing(space_xs)
+                .align_y(Alignment::Center),
+            );
+        }
 
         let (editing_i, item_edit_state) = match &self.editing_item {
             Some((i, s)) => (*i, Some(s)),
@@ -158,6 +300,81 @@ impl Editor {
                 .align_y(Alignment::Center),
             )
         }
+
+        let version = tz::catalog().and_then(|c| c.version.clone());
+        let root_desc = match (TZ_SOURCE.as_ref(), version) {
+            (Some(source), Some(version)) => format!("Timezone data: {}, {}", source, version),
+            (Some(source), None) => format!("Timezone data: {}", source),
+            (None, _) => "Timezone data: not found".to_owned(),
+        };
+        if let Some(found) = &self.found_clocks {
+            content_list = content_list.add(self.found_clocks_view(found));
+        }
+
+        content_list = content_list
+            .add(widget::settings::item(
+                "Show abbreviations in panel",
+                widget::toggler(self.show_abbreviation).on_toggle(Message::SetShowAbbreviation),
+            ))
+            .add(widget::settings::item(
+                "Default time format",
+                widget::text_input(self.fallback_format, &self.default_format_buf)
+                    .on_input(Message::DefaultFormatInput)
+                    .on_submit(Message::SetDefaultFormat),
+            ))
+            .add(widget::text::caption(format_preview(
+                None,
+                self.default_format(),
+            )))
+            .add(widget::settings::item(
+                "Clock",
+                widget::dropdown(
+                    CLOCK_OPTIONS,
+                    Some(follow_or_index(self.military_time)),
+                    |i| Message::SetMilitaryTime(follow_or(i)),
+                ),
+            ))
+            .add(widget::settings::item(
+                "Seconds",
+                widget::dropdown(
+                    SECONDS_OPTIONS,
+                    Some(follow_or_index(self.show_seconds)),
+                    |i| Message::SetShowSeconds(follow_or(i)),
+                ),
+            ))
+            .add(widget::settings::item(
+                "Clock list file",
+                widget::text_input("~/clocks.toml", &self.transfer_path)
+                    .on_input(Message::TransferPathInput),
+            ))
+            .add(
+                widget::row::with_children(vec![
+                    widget::button::text("Import and merge")
+                        .on_press(Message::Import(ImportMode::Merge))
+                        .into(),
+                    widget::button::text("Import and replace")
+                        .on_press(Message::Import(ImportMode::Replace))
+                        .into(),
+                    widget::horizontal_space().into(),
+                    widget::button::text("Export")
+                        .on_press(Message::Export)
+                        .into(),
+                ])
+                .spacing(space_xs),
+            )
+            .add(
+                widget::row::with_children(vec![
+                    widget::button::text("From GNOME Clocks")
+                        .on_press(Message::ImportFrom(Desktop::Gnome))
+                        .into(),
+                    widget::button::text("From KDE")
+                        .on_press(Message::ImportFrom(Desktop::Kde))
+                        .into(),
+                ])
+                .spacing(space_xs),
+            )
+            .add(widget::text::caption(root_desc));
+
         return content_list.into();
     }
 
@@ -188,8 +405,9 @@ impl Editor {
                             Tz {
                                 display_name: "Mordor".to_owned(),
                                 name: "Middle_Earth/Mordor".to_owned(),
+                                ..Default::default()
                             },
-                            anyhow::anyhow!("please choose a timezone"),
+                            TzError::NotChosen,
                         ));
                         let i = self.tzs.len();
                         self.tzs.push(new_tz);
@@ -205,7 +423,7 @@ impl Editor {
                     }
 
                     EditList::NewConfig(c) => {
-                        self.tzs = c.timezones.into_iter().map(validate).collect();
+                        self.set_tzs(c.timezones);
                     }
                 }
                 self.maybe_update_config();
@@ -228,9 +446,82 @@ impl Editor {
                             if new_name == mut_tz.name {
                                 break 'update_name;
                             }
-                            let new_tz = tz_from_name(new_name);
+                            let new_tz = tz_from_name(new_name, mut_tz);
                             let _ = std::mem::replace(&mut self.tzs[i], new_tz);
                         }
+                        EditItem::FixTz(new_name) => {
+                            let fixed = Tz {
+                                name: new_name,
+                                ..mut_tz.clone()
+                            };
+                            self.tzs[i] = validate(fixed);
+                        }
+                        EditItem::SetCity { zone, city } => {
+                            self.tzs[i] = validate(Tz {
+                                name: zone,
+                                display_name: city,
+                                ..mut_tz.clone()
+                            });
+                        }
+                        EditItem::SetFormat(format) => {
+                            if !tz::is_valid_format(&format) {
+                                // Keep editing, the preview says what's wrong.
+                                return None;
+                            }
+                            mut_tz.format = Some(format).filter(|f| !f.is_empty());
+                        }
+                        EditItem::SetColor(color) => {
+                            let color = color.trim();
+                            if !color.is_empty() && config::parse_color(color).is_none() {
+                                return None;
+                            }
+                            mut_tz.color = Some(color.to_owned()).filter(|c| !c.is_empty());
+                        }
+                        EditItem::SetIcon(icon) => {
+                            let icon = icon.trim();
+                            mut_tz.icon = Some(icon.to_owned()).filter(|i| !i.is_empty());
+                        }
+                        EditItem::SetShowFlag(show) => {
+                            mut_tz.show_flag = show;
+                        }
+                        EditItem::SetPlacement(placement) => {
+                            mut_tz.placement = placement;
+                        }
+                        EditItem::SetWorkingHours(on) => {
+                            mut_tz.working_hours = on.then(WorkingHours::default);
+                        }
+                        EditItem::SetHours(span) => {
+                            let (Some(hours), Some((start, end))) =
+                                (&mut mut_tz.working_hours, parse_span(&span))
+                            else {
+                                return None;
+                            };
+                            hours.start = start;
+                            hours.end = end;
+                        }
+                        EditItem::SetSleep(span) => {
+                            let (Some(hours), Some((start, end))) =
+                                (&mut mut_tz.working_hours, parse_span(&span))
+                            else {
+                                return None;
+                            };
+                            hours.sleep_start = start;
+                            hours.sleep_end = end;
+                        }
+                        EditItem::ToggleWorkday(day) => {
+                            let Some(hours) = &mut mut_tz.working_hours else {
+                                return None;
+                            };
+                            match hours.days.iter().position(|d| *d == day) {
+                                Some(pos) => {
+                                    hours.days.remove(pos);
+                                }
+                                None => {
+                                    hours.days.push(day);
+                                    hours.days.sort_by_key(Weekday::num_days_from_monday);
+                                }
+                            }
+                        }
                     }
                 }
                 self.editing_item = None;
@@ -248,8 +539,35 @@ impl Editor {
                     ItemEditState::DisplayName => {
                         self.text_input_buf = tz.display_name.to_owned();
                     }
+                    ItemEditState::Format => {
+                        self.text_input_buf = tz.format.clone().unwrap_or_default();
+                    }
+                    ItemEditState::Color => {
+                        self.text_input_buf = tz.color.clone().unwrap_or_default();
+                    }
+                    ItemEditState::Icon => {
+                        self.text_input_buf = tz.icon.clone().unwrap_or_default();
+                    }
+                    ItemEditState::Hours => {
+                        self.text_input_buf = tz
+                            .working_hours
+                            .as_ref()
+                            .map(|h| format_span(h.start, h.end))
+                            .unwrap_or_default();
+                    }
+                    ItemEditState::Sleep => {
+                        self.text_input_buf = tz
+                            .working_hours
+                            .as_ref()
+                            .map(|h| format_span(h.sleep_start, h.sleep_end))
+                            .unwrap_or_default();
+                    }
                     ItemEditState::Name => {
-                        self.tz_input_buf = Some(tz.name.to_owned());
+                        let zone = tz::catalog()
+                            .and_then(|c| c.get(&tz.name).cloned())
+                            .unwrap_or_else(|| ZoneInfo::named(&tz.name));
+                        self.tz_input_buf = Some(CatalogEntry::Zone(zone));
+                        self.tz_typed.clear();
                     }
                 };
                 self.editing_item = Some((i, ie));
@@ -265,24 +583,393 @@ impl Editor {
                 self.text_input_buf = s;
                 return None;
             }
+
+            Message::TzInput(s) => {
+                self.tz_typed = s;
+                return None;
+            }
+
+            Message::DismissNotice => {
+                self.notice = None;
+                return None;
+            }
+
+            Message::DefaultFormatInput(s) => {
+                self.default_format_buf = s;
+                return None;
+            }
+
+            Message::SetDefaultFormat(format) => {
+                if !tz::is_valid_format(&format) {
+                    return None;
+                }
+                let format = Some(format).filter(|f| !f.is_empty());
+                self.set("default_format", format);
+                return None;
+            }
+
+            Message::SetMilitaryTime(military_time) => {
+                self.military_time = military_time;
+                self.set("military_time", military_time);
+                return None;
+            }
+
+            Message::SetShowSeconds(show_seconds) => {
+                self.show_seconds = show_seconds;
+                self.set("show_seconds", show_seconds);
+                return None;
+            }
+
+            Message::TransferPathInput(s) => {
+                self.transfer_path = s;
+                return None;
+            }
+
+            Message::Export => {
+                let Some((_, config)) = self.stored_config() else {
+                    return None;
+                };
+                let path = expand_home(&self.transfer_path);
+                self.notice = Some(match config::export(&config, &path) {
+                    Ok(()) => format!(
+                        "Exported {} clocks to {}",
+                        config.timezones.len(),
+                        path.display()
+                    ),
+                    Err(err) => format!("{:#}", err),
+                });
+                return None;
+            }
+
+            Message::Import(mode) => {
+                let Some((app_config, mut config)) = self.stored_config() else {
+                    return None;
+                };
+                let path = expand_home(&self.transfer_path);
+                let res = config::import(&path).and_then(|list| {
+                    let added = list.apply(&mut config, mode);
+                    config.write_entry(&app_config)?;
+                    Ok(added)
+                });
+                self.notice = Some(match res {
+                    Ok(added) => {
+                        self.load_settings(config);
+                        format!("Imported {} clocks from {}", added, path.display())
+                    }
+                    Err(err) => format!("{:#}", err),
+                });
+                return None;
+            }
+
+            Message::ImportFrom(desktop) => {
+                let Some(catalog) = tz::catalog() else {
+                    self.notice = Some("Still loading timezones, try again in a moment".to_owned());
+                    return None;
+                };
+                // An empty path means wherever that desktop keeps its settings.
+                let path = Some(&self.transfer_path)
+                    .filter(|p| !p.is_empty())
+                    .map(|p| expand_home(p));
+                match config::find_clocks(desktop, path.as_deref(), &catalog) {
+                    Ok(found) => self.found_clocks = Some(found),
+                    Err(err) => self.notice = Some(format!("{:#}", err)),
+                }
+                return None;
+            }
+
+            Message::AddFoundClocks => {
+                let Some(found) = self.found_clocks.take() else {
+                    return None;
+                };
+                let Some((_, mut config)) = self.stored_config() else {
+                    return None;
+                };
+                let before = config.timezones.len();
+                for tz in found.into_iter().filter_map(|f| f.clock.ok()) {
+                    if !config.timezones.contains(&tz) {
+                        config.timezones.push(tz);
+                    }
+                }
+                let added = config.timezones.len() - before;
+                self.set("timezones", &config.timezones);
+                self.set_tzs(config.timezones);
+                self.notice = Some(format!("Added {} clocks", added));
+                return None;
+            }
+
+            Message::DiscardFoundClocks => {
+                self.found_clocks = None;
+                return None;
+            }
+
+            Message::ProfileNameInput(s) => {
+                self.profile_name_buf = s;
+                return None;
+            }
+
+            Message::EditProfile(ep) => {
+                let Some((app_config, mut config)) = self.stored_config() else {
+                    return None;
+                };
+                let active = config.active_profile.clone();
+                let res = match ep {
+                    EditProfile::Switch(name) => config.switch_profile(&name),
+                    EditProfile::Create(name) => config.create_profile(&name, Vec::new()),
+                    EditProfile::Duplicate(name) => {
+                        let tzs = config.timezones.clone();
+                        config.create_profile(&name, tzs)
+                    }
+                    EditProfile::Rename(name) => config.rename_profile(&active, &name),
+                    EditProfile::Delete => config.delete_profile(&active),
+                };
+                match res.and_then(|()| config.write_profiles(&app_config)) {
+                    Ok(()) => {
+                        self.profile_name_buf.clear();
+                        self.editing_item = None;
+                        self.load_settings(config.clone());
+                        return Some(Output::NewConfig(config));
+                    }
+                    Err(err) => {
+                        self.notice = Some(format!("{:#}", err));
+                        return None;
+                    }
+                }
+            }
+
+            Message::SetShowAbbreviation(show) => {
+                self.show_abbreviation = show;
+                self.set("show_abbreviation", show);
+                return None;
+            }
         }
     }
 
-    fn maybe_update_config(&self) {
-        let maybe_tzs: Option<Vec<Tz>> = self
-            .tzs
+    /// Picks up a newly loaded catalog, filling the picker, migrating renamed zones
+    /// and re-checking every clock.
+    pub(super) fn reload_zones(&mut self) {
+        let Some(catalog) = tz::catalog() else {
+            return;
+        };
+        self.tz_input_state = Some(combo_box::State::new(catalog.entries()));
+        let mut tzs: Vec<Tz> = std::mem::take(&mut self.tzs)
+            .into_iter()
+            .map(|t| match t {
+                Ok(tz) => tz,
+                Err((tz, _)) => tz,
+            })
+            .collect();
+        let mut renamed: Vec<_> = tzs
+            .iter_mut()
+            .filter_map(|tz| tz.migrate_id(&catalog))
+            .collect();
+        self.tzs = tzs.into_iter().map(validate).collect();
+        if let Some(app_config) = &self.app_config {
+            // Both keys in one transaction, like `WorldClocksConfig::write_profiles`.
+            let tx = app_config.transaction();
+            let mut res = Ok(());
+            if !renamed.is_empty() {
+                // Like any other edit, only saved once every clock is valid.
+                if let Some(tzs) = self.valid_tzs() {
+                    res = res.and(tx.set("timezones", tzs));
+                }
+            }
+            // The other profiles aren't in the editor, so fix them straight in the config.
+            let mut config = WorldClocksConfig::load(app_config);
+            let in_profiles = config.migrate_profile_zone_ids(&catalog);
+            if !in_profiles.is_empty() {
+                res = res.and(tx.set("profiles", &config.profiles));
+                renamed.extend(in_profiles);
+            }
+            if let Err(err) = res.and_then(|()| tx.commit()) {
+                tracing::error!(?err, "Error writing renamed timezones");
+            }
+        }
+        if !renamed.is_empty() {
+            let list = renamed
+                .iter()
+                .map(|(old, new)| format!("{} → {}", old, new))
+                .join(", ");
+            self.notice = Some(format!("Updated renamed timezones: {}", list));
+        }
+    }
+
+    pub(super) fn set_fallback_format(&mut self, format: &'static str) {
+        self.fallback_format = format;
+    }
+
+    /// Writes one key, logging any error. Does nothing without a config, the app has already
+    /// said why.
+    fn set<T: Serialize>(&self, key: &str, value: T) {
+        let Some(app_config) = &self.app_config else {
+            return;
+        };
+        if let Err(err) = app_config.set(key, value) {
+            tracing::error!(?err, key, "Error writing config");
+        }
+    }
+
+    /// The stored config and a handle to write it back, for changes beyond the clock list.
+    fn stored_config(&mut self) -> Option<(cosmic_config::Config, WorldClocksConfig)> {
+        let Some(app_config) = self.app_config.clone() else {
+            self.notice = Some("Settings couldn't be opened, so this can't be saved".to_owned());
+            return None;
+        };
+        let config = WorldClocksConfig::load(&app_config);
+        Some((app_config, config))
+    }
+
+    /// What clocks without their own format use, as currently typed.
+    fn default_format(&self) -> &str {
+        if self.default_format_buf.is_empty() {
+            self.fallback_format
+        } else {
+            &self.default_format_buf
+        }
+    }
+
+    /// The clock list, if every clock in it is valid.
+    fn valid_tzs(&self) -> Option<Vec<Tz>> {
+        self.tzs
             .iter()
             .map(|r| r.as_ref().ok())
             .map(|tz| tz.map(|tz| tz.clone()))
-            .collect();
-        match maybe_tzs {
-            Some(tzs) => WorldClocksConfig { timezones: tzs }
-                .write_entry(&self.app_config)
-                .unwrap(),
+            .collect()
+    }
+
+    fn maybe_update_config(&self) {
+        match self.valid_tzs() {
+            // Only write the list, so we don't clobber settings the editor doesn't manage.
+            Some(tzs) => self.set("timezones", tzs),
             None => {}
         }
     }
 
+    /// The profile picker, and a name field shared by new, duplicate and rename.
+    fn profiles_view(&self, spacing: u16) -> Element<Message> {
+        let names = self.profile_names.clone();
+        let picker = widget::dropdown(
+            &self.profile_names,
+            self.profile_names
+                .iter()
+                .position(|n| *n == self.active_profile),
+            move |i| Message::EditProfile(EditProfile::Switch(names[i].clone())),
+        );
+        let name = self.profile_name_buf.trim();
+        let with_name = |ep: fn(String) -> EditProfile| {
+            (!name.is_empty()).then(|| Message::EditProfile(ep(name.to_owned())))
+        };
+        widget::column()
+            .push(widget::settings::item("Profile", picker))
+            .push(
+                widget::row::with_children(vec![
+                    widget::text_input("Profile name", &self.profile_name_buf)
+                        .on_input(Message::ProfileNameInput)
+                        .width(Length::Fill)
+                        .into(),
+                    widget::button::text("New")
+                        .on_press_maybe(with_name(EditProfile::Create))
+                        .into(),
+                    widget::button::text("Duplicate")
+                        .on_press_maybe(with_name(EditProfile::Duplicate))
+                        .into(),
+                    widget::button::text("Rename")
+                        .on_press_maybe(with_name(EditProfile::Rename))
+                        .into(),
+                    widget::button::destructive("Delete")
+                        .on_press_maybe(
+                            (self.profile_names.len() > 1)
+                                .then_some(Message::EditProfile(EditProfile::Delete)),
+                        )
+                        .into(),
+                ])
+                .spacing(spacing)
+                .align_y(Alignment::Center),
+            )
+            .into()
+    }
+
+    /// Each clock from another desktop and what it'll become, before any are added.
+    fn found_clocks_view<'a>(&'a self, found: &'a [FoundClock]) -> Element<'a, Message> {
+        let mut column = widget::column().push(widget::text::body("Clocks to add"));
+        for f in found {
+            let line = match &f.clock {
+                Ok(tz) if tz.display_name == tz.name => tz.name.clone(),
+                Ok(tz) => format!("{} · {}", tz.display_name, tz.name),
+                Err(err) => format!("{}: {}", f.source, err),
+            };
+            column = column.push(widget::text::caption(line));
+        }
+        let any_ok = found.iter().any(|f| f.clock.is_ok());
+        column
+            .push(widget::row::with_children(vec![
+                widget::button::text("Add")
+                    .on_press_maybe(any_ok.then_some(Message::AddFoundClocks))
+                    .into(),
+                widget::button::text("Cancel")
+                    .on_press(Message::DiscardFoundClocks)
+                    .into(),
+            ]))
+            .into()
+    }
+
+    /// A clock's working hours, sleeping hours and working days.
+    fn working_hours_view<'a>(
+        &'a self,
+        i: usize,
+        hours: &WorkingHours,
+        editing: Option<&ItemEditState>,
+    ) -> Element<'a, Message> {
+        let hours_widget: Element<_> = if let Some(&ItemEditState::Hours) = editing {
+            widget::inline_input("09:00–17:00", &self.text_input_buf)
+                .editing(true)
+                .on_input(Message::Input)
+                .on_unfocus(Message::CancelEditing)
+                .on_submit(move |s| Message::EditItem(i, EditItem::SetHours(s)))
+                .into()
+        } else {
+            let label = format!("Working: {}", format_span(hours.start, hours.end));
+            widget::button::custom(widget::text::caption(label))
+                .class(cosmic::theme::Button::Text)
+                .on_press(Message::StartEditing(i, ItemEditState::Hours))
+                .into()
+        };
+
+        let sleep_widget: Element<_> = if let Some(&ItemEditState::Sleep) = editing {
+            widget::inline_input("23:00–07:00", &self.text_input_buf)
+                .editing(true)
+                .on_input(Message::Input)
+                .on_unfocus(Message::CancelEditing)
+                .on_submit(move |s| Message::EditItem(i, EditItem::SetSleep(s)))
+                .into()
+        } else {
+            let label = format!(
+                "Asleep: {}",
+                format_span(hours.sleep_start, hours.sleep_end)
+            );
+            widget::button::custom(widget::text::caption(label))
+                .class(cosmic::theme::Button::Text)
+                .on_press(Message::StartEditing(i, ItemEditState::Sleep))
+                .into()
+        };
+
+        let days = WEEKDAYS.iter().map(|&day| {
+            let class = if hours.days.contains(&day) {
+                cosmic::theme::Button::Suggested
+            } else {
+                cosmic::theme::Button::Standard
+            };
+            widget::button::text(day.to_string())
+                .class(class)
+                .on_press(Message::EditItem(i, EditItem::ToggleWorkday(day)))
+                .into()
+        });
+
+        widget::column()
+            .push(widget::row::with_children(vec![hours_widget, sleep_widget]))
+            .push(widget::row::with_children(days.collect()))
+            .into()
+    }
+
     fn tz_list_item<'a>(
         &'a self,
         i: usize,
@@ -305,32 +992,162 @@ impl Editor {
                 .into()
         };
 
-        let tz_name_widget: Element<_> = if let Some(&ItemEditState::Name) = editing {
-            widget::combo_box(
-                &self.tz_input_state,
-                "Timezone Name",
-                self.tz_input_buf.as_ref(),
-                move |s| Message::EditItem(i, EditItem::SetTz(s)),
-            )
-            .on_close(Message::CancelEditing)
-            .into()
-        } else {
-            widget::button::custom(widget::text::caption(&tz.name))
+        let tz_name_widget: Element<_> = match (editing, &self.tz_input_state) {
+            (Some(&ItemEditState::Name), None) => {
+                widget::text::caption("Loading timezones…").into()
+            }
+            (Some(&ItemEditState::Name), Some(tz_input_state)) => {
+                let picker = widget::combo_box(
+                    tz_input_state,
+                    "Timezone Name",
+                    self.tz_input_buf.as_ref(),
+                    move |entry| {
+                        let edit = match entry {
+                            CatalogEntry::Zone(z) => EditItem::SetTz(z.canonical_name().to_owned()),
+                            CatalogEntry::City(c) => EditItem::SetCity {
+                                zone: tz::catalog().map_or(c.zone.clone(), |catalog| {
+                                    catalog.canonical_name(&c.zone).to_owned()
+                                }),
+                                city: c.name,
+                            },
+                        };
+                        Message::EditItem(i, edit)
+                    },
+                )
+                .on_input(Message::TzInput)
+                .on_close(Message::CancelEditing);
+                // Offsets aren't in the list, so offer whatever was typed if it is one.
+                // Pressed on the way down, as releasing would be after the picker has closed.
+                let offset = tz::typed_offset(&self.tz_typed).map(|name| {
+                    widget::button::text(format!("Use {}", name))
+                        .on_press_down(Message::EditItem(i, EditItem::SetTz(name)))
+                });
+                widget::column().push(picker).push_maybe(offset).into()
+            }
+            _ => widget::button::custom(widget::text::caption(&tz.name))
                 .class(cosmic::theme::Button::Text)
                 .on_press(Message::StartEditing(i, ItemEditState::Name))
+                .into(),
+        };
+
+        let format_widget: Element<_> = if let Some(&ItemEditState::Format) = editing {
+            let format = if self.text_input_buf.is_empty() {
+                self.default_format()
+            } else {
+                &self.text_input_buf
+            };
+            widget::column()
+                .push(
+                    widget::inline_input(self.default_format(), &self.text_input_buf)
+                        .editing(true)
+                        .on_input(Message::Input)
+                        .on_unfocus(Message::CancelEditing)
+                        .on_submit(move |s| Message::EditItem(i, EditItem::SetFormat(s))),
+                )
+                .push(widget::text::caption(format_preview(
+                    Some(&tz.name),
+                    format,
+                )))
+                .into()
+        } else {
+            let label = match &tz.format {
+                Some(format) => format!("Format: {}", format),
+                None => "Default format".to_owned(),
+            };
+            widget::button::custom(widget::text::caption(label))
+                .class(cosmic::theme::Button::Text)
+                .on_press(Message::StartEditing(i, ItemEditState::Format))
+                .into()
+        };
+
+        let color_widget: Element<_> = if let Some(&ItemEditState::Color) = editing {
+            widget::inline_input("#rrggbb", &self.text_input_buf)
+                .editing(true)
+                .on_input(Message::Input)
+                .on_unfocus(Message::CancelEditing)
+                .on_submit(move |s| Message::EditItem(i, EditItem::SetColor(s)))
+                .into()
+        } else {
+            let label = match &tz.color {
+                Some(color) => format!("Color: {}", color),
+                None => "No color".to_owned(),
+            };
+            widget::button::custom(widget::text::caption(label))
+                .class(cosmic::theme::Button::Text)
+                .on_press(Message::StartEditing(i, ItemEditState::Color))
                 .into()
         };
 
+        let icon_widget: Element<_> = if let Some(&ItemEditState::Icon) = editing {
+            widget::inline_input("Icon name", &self.text_input_buf)
+                .editing(true)
+                .on_input(Message::Input)
+                .on_unfocus(Message::CancelEditing)
+                .on_submit(move |s| Message::EditItem(i, EditItem::SetIcon(s)))
+                .into()
+        } else {
+            let label = match &tz.icon {
+                Some(icon) => format!("Icon: {}", icon),
+                None => "No icon".to_owned(),
+            };
+            widget::button::custom(widget::text::caption(label))
+                .class(cosmic::theme::Button::Text)
+                .on_press(Message::StartEditing(i, ItemEditState::Icon))
+                .into()
+        };
+
+        let flag_widget = widget::toggler(tz.show_flag)
+            .label("Flag")
+            .on_toggle(move |show| Message::EditItem(i, EditItem::SetShowFlag(show)));
+
+        let placement_widget = widget::dropdown(
+            PLACEMENT_OPTIONS,
+            Placement::ALL.iter().position(|p| *p == tz.placement),
+            move |p| Message::EditItem(i, EditItem::SetPlacement(Placement::ALL[p])),
+        );
+
+        let working_hours_widget = widget::toggler(tz.working_hours.is_some())
+            .label("Working hours")
+            .on_toggle(move |on| Message::EditItem(i, EditItem::SetWorkingHours(on)));
+
         return widget::column()
             .push(display_name_widget)
             // .push(widget::text::caption(&tz.name))
             .push(tz_name_widget)
-            .push_maybe(err.map(|e| widget::text::text(e.to_string())))
+            .push(format_widget)
+            .push(placement_widget)
+            .push(working_hours_widget)
+            .push_maybe(
+                tz.working_hours
+                    .as_ref()
+                    .map(|hours| self.working_hours_view(i, hours, editing)),
+            )
+            .push(widget::row::with_children(vec![
+                color_widget,
+                icon_widget,
+                flag_widget.into(),
+            ]))
+            .push_maybe(err.map(|e| widget::text::caption(e.to_string())))
+            .push_maybe(err.and_then(TzError::suggestion).map(|s| {
+                widget::button::text(fl!("use-suggestion", name = s))
+                    .on_press(Message::EditItem(i, EditItem::FixTz(s.to_owned())))
+            }))
             .into();
     }
 }
 
-fn maybetz_to_option(maybe_tz: &MaybeTz) -> (&Tz, Option<&anyhow::Error>) {
+/// `09:00-17:00`, with any spaces or an en dash.
+fn parse_span(s: &str) -> Option<(NaiveTime, NaiveTime)> {
+    let (start, end) = s.split_once(['-', '–'])?;
+    let time = |t: &str| NaiveTime::parse_from_str(t.trim(), "%H:%M").ok();
+    Some((time(start)?, time(end)?))
+}
+
+fn format_span(start: NaiveTime, end: NaiveTime) -> String {
+    format!("{}–{}", start.format("%H:%M"), end.format("%H:%M"))
+}
+
+fn maybetz_to_option(maybe_tz: &MaybeTz) -> (&Tz, Option<&TzError>) {
     match maybe_tz {
         Ok(tz) => {
             return (tz, None);
@@ -348,10 +1165,50 @@ fn validate(tz: Tz) -> MaybeTz {
     };
 }
 
-fn tz_from_name(name: String) -> MaybeTz {
-    let display_name = name.rsplitn(2, "/").next().unwrap().replace("_", " ");
+/// A clock for a newly picked zone, keeping `like`'s format and appearance.
+fn tz_from_name(name: String, like: &Tz) -> MaybeTz {
+    let display_name = tz::default_display_name(&name);
     return validate(Tz {
         name: name,
         display_name: display_name,
+        ..like.clone()
     });
 }
+
+/// So `~/clocks.toml` works as it would in a shell.
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Dropdown index to setting: follow the system, on, or off.
+fn follow_or(i: usize) -> Option<bool> {
+    match i {
+        1 => Some(true),
+        2 => Some(false),
+        _ => None,
+    }
+}
+
+fn follow_or_index(setting: Option<bool>) -> usize {
+    match setting {
+        None => 0,
+        Some(true) => 1,
+        Some(false) => 2,
+    }
+}
+
+/// e.g. "Preview: Mon 14:05", in `zone` or local time.
+fn format_preview(zone: Option<&str>, format: &str) -> String {
+    if !tz::is_valid_format(format) {
+        return "Invalid format".to_owned();
+    }
+    let now = chrono::Utc::now();
+    let preview = match zone.map(|z| ValidTz::from_names(z, "")) {
+        Some(Ok(tz)) => tz.format(now, format),
+        _ => tz::try_format(&now.with_timezone(&chrono::Local), format).unwrap_or_default(),
+    };
+    return format!("Preview: {}", preview);
+}
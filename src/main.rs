@@ -3,6 +3,7 @@
 use app::YourApp;
 /// The `app` module is used by convention to indicate the main component of our application.
 mod app;
+mod cli;
 mod config;
 mod core;
 mod editor;
@@ -16,6 +17,12 @@ mod tz;
 fn main() -> cosmic::iced::Result {
     tracing_subscriber::fmt::init();
     let _ = tracing_log::LogTracer::init();
+    core::localization::init(&i18n_embed::DesktopLanguageRequester::requested_languages());
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args));
+    }
 
     cosmic::applet::run::<YourApp>(())
 }
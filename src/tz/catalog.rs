@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use super::cities::{self, City};
+use super::links::find_links;
+use super::renames::RENAMED;
+use super::TzSource;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Coordinates {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl Coordinates {
+    /// Great-circle distance as an angle in radians, which is all we need for comparing.
+    fn distance_to(self, other: Coordinates) -> f64 {
+        let (lat1, lat2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let dlat = lat2 - lat1;
+        let dlon = (other.longitude - self.longitude).to_radians();
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+        2.0 * a.sqrt().asin()
+    }
+}
+
+/// A zone, with whatever `zone1970.tab` / `zone.tab` know about it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZoneInfo {
+    pub name: String,
+    pub country_codes: Vec<String>,
+    pub country_names: Vec<String>,
+    /// Of the zone's principal location.
+    pub coordinates: Option<Coordinates>,
+    /// e.g. "Mountain (most areas)"
+    pub comment: Option<String>,
+    /// Set when this name is only kept for backwards compatibility, e.g. `US/Pacific` ->
+    /// `America/Los_Angeles`. Links that zone.tab still lists, like `Europe/Oslo`, are zones in
+    /// their own right and don't have it.
+    pub alias_of: Option<String>,
+}
+
+/// Something the timezone picker can offer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CatalogEntry {
+    Zone(ZoneInfo),
+    City(City),
+}
+
+#[derive(Default)]
+pub struct Catalog {
+    pub zones: Vec<ZoneInfo>,
+    pub cities: Vec<City>,
+    by_name: HashMap<String, usize>,
+    /// Links that are only kept for backwards compatibility, `old -> current`.
+    deprecated: HashMap<String, String>,
+    /// The tzdata release, e.g. "2025b", if it says.
+    pub version: Option<String>,
+}
+
+struct TabRow {
+    country_codes: Vec<String>,
+    coordinates: Option<Coordinates>,
+    comment: Option<String>,
+}
+
+impl Catalog {
+    pub fn load(source: &TzSource, names: &[String]) -> Self {
+        let countries = read_tab(source, "iso3166.tab")
+            .map(|s| parse_iso3166(&s))
+            .unwrap_or_default();
+        let mut rows = read_tab(source, "zone1970.tab")
+            .map(|s| parse_zone_tab(&s))
+            .unwrap_or_default();
+        // zone.tab has one row per country, so it still knows about zones zone1970.tab merged away.
+        for (name, row) in read_tab(source, "zone.tab")
+            .map(|s| parse_zone_tab(&s))
+            .unwrap_or_default()
+        {
+            rows.entry(name).or_insert(row);
+        }
+
+        let links = find_links(source, names, |n| rows.contains_key(n));
+        // A link that's still in zone.tab is a country's zone in its own right (e.g. Europe/Oslo),
+        // the rest are old names.
+        let deprecated: HashMap<String, String> = links
+            .iter()
+            .filter(|(link, _)| !rows.contains_key(*link))
+            .map(|(link, target)| (link.clone(), target.clone()))
+            .collect();
+
+        let mut zones: Vec<ZoneInfo> = names
+            .iter()
+            .map(|name| {
+                let alias_of = deprecated.get(name).cloned();
+                // Aliases mostly aren't in the tables, so borrow what we know about the target.
+                let row = rows
+                    .get(name)
+                    .or_else(|| alias_of.as_ref().and_then(|t| rows.get(t)));
+                let country_codes = row.map(|r| r.country_codes.clone()).unwrap_or_default();
+                let country_names = country_codes
+                    .iter()
+                    .filter_map(|c| countries.get(c).cloned())
+                    .collect();
+                ZoneInfo {
+                    name: name.clone(),
+                    country_codes,
+                    country_names,
+                    coordinates: row.and_then(|r| r.coordinates),
+                    comment: row.and_then(|r| r.comment.clone()),
+                    alias_of,
+                }
+            })
+            .collect();
+        // Canonical zones first, aliases after them.
+        zones.sort_by(|a, b| {
+            a.alias_of
+                .is_some()
+                .cmp(&b.alias_of.is_some())
+                .then_with(|| a.name.cmp(&b.name))
+        });
+
+        let by_name = zones
+            .iter()
+            .enumerate()
+            .map(|(i, z)| (z.name.clone(), i))
+            .collect();
+        Catalog {
+            zones,
+            cities: cities::load(&countries),
+            by_name,
+            deprecated,
+            version: source.version(),
+        }
+    }
+
+    /// Zones, then cities, then aliases.
+    pub fn entries(&self) -> Vec<CatalogEntry> {
+        let (zones, aliases): (Vec<_>, Vec<_>) =
+            self.zones.iter().partition(|z| z.alias_of.is_none());
+        let zones = zones.into_iter().cloned().map(CatalogEntry::Zone);
+        let cities = self.cities.iter().cloned().map(CatalogEntry::City);
+        let aliases = aliases.into_iter().cloned().map(CatalogEntry::Zone);
+        zones.chain(cities).chain(aliases).collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ZoneInfo> {
+        self.by_name.get(name).map(|&i| &self.zones[i])
+    }
+
+    /// Follows old names, e.g. `US/Pacific` gives `America/Los_Angeles`, but leaves links
+    /// that are still a country's zone alone, e.g. `Europe/Oslo`.
+    pub fn canonical_name<'a>(&'a self, name: &'a str) -> &'a str {
+        self.get(name).map_or(name, ZoneInfo::canonical_name)
+    }
+
+    /// The flag of the zone's country. Links keep their own country where zone.tab lists
+    /// them, e.g. Europe/Oslo is Norwegian though tzdb links it to Europe/Berlin.
+    pub fn flag(&self, name: &str) -> Option<String> {
+        self.get(name)?.flag()
+    }
+
+    /// A city from our list by name or alternate name, ignoring case.
+    pub fn find_city(&self, name: &str) -> Option<&City> {
+        let matches = |n: &String| n.to_lowercase() == name.to_lowercase();
+        self.cities
+            .iter()
+            .find(|c| matches(&c.name) || c.alternate_names.iter().any(matches))
+    }
+
+    /// The zone whose principal location is closest, for places we only know the position of.
+    pub fn nearest_zone(&self, at: Coordinates) -> Option<&ZoneInfo> {
+        self.zones
+            .iter()
+            .filter(|z| z.alias_of.is_none())
+            .filter_map(|z| Some((z, z.coordinates?.distance_to(at))))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(z, _)| z)
+    }
+
+    /// The current ID for a renamed or deprecated zone, e.g. `Europe/Kiev` gives `Europe/Kyiv`.
+    pub fn current_name(&self, name: &str) -> Option<&str> {
+        let renamed = RENAMED
+            .iter()
+            .find(|(old, _)| *old == name)
+            .map(|(_, new)| *new)
+            // Don't rename to something an older tzdata doesn't have yet.
+            .filter(|new| self.get(new).is_some());
+        renamed.or_else(|| self.deprecated.get(name).map(String::as_str))
+    }
+}
+
+impl ZoneInfo {
+    /// A bare entry for zones we only know by name.
+    pub fn named(name: &str) -> Self {
+        ZoneInfo {
+            name: name.to_owned(),
+            country_codes: Vec::new(),
+            country_names: Vec::new(),
+            coordinates: None,
+            comment: None,
+            alias_of: None,
+        }
+    }
+
+    pub fn canonical_name(&self) -> &str {
+        self.alias_of.as_deref().unwrap_or(&self.name)
+    }
+
+    /// The first country's flag as an emoji, made of regional indicator symbols.
+    pub fn flag(&self) -> Option<String> {
+        let code = self.country_codes.first()?;
+        if code.len() != 2 || !code.bytes().all(|b| b.is_ascii_uppercase()) {
+            return None;
+        }
+        code.bytes()
+            .map(|b| char::from_u32(0x1F1E6 + u32::from(b - b'A')))
+            .collect()
+    }
+}
+
+impl fmt::Display for CatalogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CatalogEntry::Zone(z) => z.fmt(f),
+            CatalogEntry::City(c) => c.fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for ZoneInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)?;
+        if let Some(target) = &self.alias_of {
+            write!(f, " → {}", target)?;
+        }
+        if !self.country_names.is_empty() {
+            write!(f, " · {}", self.country_names.join(", "))?;
+        }
+        if let Some(comment) = &self.comment {
+            write!(f, " · {}", comment)?;
+        }
+        Ok(())
+    }
+}
+
+fn read_tab(source: &TzSource, file: &str) -> Option<String> {
+    match source.read(file) {
+        Ok(bytes) => Some(String::from_utf8_lossy(&bytes).into_owned()),
+        Err(err) => {
+            tracing::warn!(file, ?err, "Couldn't read tz table");
+            None
+        }
+    }
+}
+
+fn data_lines(s: &str) -> impl Iterator<Item = &str> {
+    s.lines()
+        .filter(|l| !l.starts_with('#') && !l.trim().is_empty())
+}
+
+fn parse_iso3166(s: &str) -> HashMap<String, String> {
+    data_lines(s)
+        .filter_map(|l| {
+            let (code, name) = l.split_once('\t')?;
+            Some((code.to_owned(), name.trim().to_owned()))
+        })
+        .collect()
+}
+
+/// Parses both `zone1970.tab` (comma-separated codes) and `zone.tab` (a single code).
+fn parse_zone_tab(s: &str) -> HashMap<String, TabRow> {
+    data_lines(s)
+        .filter_map(|l| {
+            let mut cols = l.split('\t');
+            let codes = cols.next()?;
+            let coordinates = parse_coordinates(cols.next()?);
+            let name = cols.next()?;
+            let comment = cols
+                .next()
+                .map(|c| c.trim().to_owned())
+                .filter(|c| !c.is_empty());
+            let row = TabRow {
+                country_codes: codes.split(',').map(str::to_owned).collect(),
+                coordinates,
+                comment,
+            };
+            Some((name.to_owned(), row))
+        })
+        .collect()
+}
+
+/// ISO 6709 as used by tzdata, `±DDMM±DDDMM` or `±DDMMSS±DDDMMSS`.
+fn parse_coordinates(s: &str) -> Option<Coordinates> {
+    let split = s.get(1..)?.find(['+', '-'])? + 1;
+    let (lat, lon) = s.split_at(split);
+    Some(Coordinates {
+        latitude: parse_degrees(lat, 2)?,
+        longitude: parse_degrees(lon, 3)?,
+    })
+}
+
+fn parse_degrees(s: &str, degree_digits: usize) -> Option<f64> {
+    let sign = match s.as_bytes().first()? {
+        b'+' => 1.0,
+        b'-' => -1.0,
+        _ => return None,
+    };
+    let digits = &s[1..];
+    if !digits.bytes().all(|b| b.is_ascii_digit()) || digits.len() < degree_digits + 2 {
+        return None;
+    }
+    let (deg, rest) = digits.split_at(degree_digits);
+    let (min, sec) = rest.split_at(2);
+    let deg: f64 = deg.parse().ok()?;
+    let min: f64 = min.parse().ok()?;
+    let sec: f64 = if sec.is_empty() {
+        0.0
+    } else {
+        sec.parse().ok()?
+    };
+    Some(sign * (deg + min / 60.0 + sec / 3600.0))
+}
@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+use std::fmt;
+
+static CITIES_TSV: &str = include_str!("../../res/cities.tsv");
+
+/// A city from our offline list, so the picker can find zones by more than their path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct City {
+    pub name: String,
+    pub alternate_names: Vec<String>,
+    pub country_code: String,
+    pub country_name: Option<String>,
+    pub zone: String,
+}
+
+pub fn load(countries: &HashMap<String, String>) -> Vec<City> {
+    CITIES_TSV
+        .lines()
+        .filter(|l| !l.starts_with('#') && !l.trim().is_empty())
+        .filter_map(|l| {
+            let mut cols = l.split('\t');
+            let name = cols.next()?;
+            let alternate_names = cols
+                .next()?
+                .split(',')
+                .filter(|n| !n.is_empty())
+                .map(str::to_owned)
+                .collect();
+            let country_code = cols.next()?;
+            let zone = cols.next()?;
+            Some(City {
+                name: name.to_owned(),
+                alternate_names,
+                country_code: country_code.to_owned(),
+                country_name: countries.get(country_code).cloned(),
+                zone: zone.to_owned(),
+            })
+        })
+        .collect()
+}
+
+impl fmt::Display for City {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)?;
+        // Shown so they're searchable, e.g. "Zurich" finds Zürich.
+        if !self.alternate_names.is_empty() {
+            write!(f, " ({})", self.alternate_names.join(", "))?;
+        }
+        write!(
+            f,
+            " · {} · {}",
+            self.country_name.as_deref().unwrap_or(&self.country_code),
+            self.zone
+        )
+    }
+}
@@ -0,0 +1,110 @@
+use std::fmt;
+use std::io;
+
+use crate::fl;
+
+/// Why a clock's zone couldn't be loaded. Displays as a localized message for the UI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TzError {
+    /// No zoneinfo root was found and there's no bundled copy to fall back on.
+    NoDatabase,
+    /// A new clock that hasn't been given a zone yet.
+    NotChosen,
+    NotFound {
+        name: String,
+        /// The zone the user probably meant, e.g. `Europe/Kyiv` for `Europe/Kiev`.
+        suggestion: Option<String>,
+    },
+    PermissionDenied {
+        name: String,
+    },
+    /// A TZif file we couldn't parse. `reason` is only for the logs.
+    Corrupt {
+        name: String,
+        #[allow(dead_code)]
+        reason: String,
+    },
+    /// Not a TZif file, or a TZif version we don't know.
+    Unsupported {
+        name: String,
+    },
+    /// Not a zone name, fixed offset or POSIX TZ string.
+    InvalidName {
+        name: String,
+        #[allow(dead_code)]
+        reason: String,
+    },
+    /// A name that resolves to somewhere outside the zoneinfo root, e.g. through a symlink.
+    OutsideRoot {
+        name: String,
+    },
+    Io {
+        name: String,
+        reason: String,
+    },
+}
+
+impl TzError {
+    pub(super) fn from_io(name: &str, err: io::Error) -> Self {
+        let name = name.to_owned();
+        match err.kind() {
+            io::ErrorKind::NotFound => TzError::NotFound {
+                name,
+                suggestion: None,
+            },
+            io::ErrorKind::PermissionDenied => TzError::PermissionDenied { name },
+            _ => TzError::Io {
+                name,
+                reason: err.to_string(),
+            },
+        }
+    }
+
+    /// A zone name that would fix this, if we can think of one.
+    pub fn suggestion(&self) -> Option<&str> {
+        match self {
+            TzError::NotFound { suggestion, .. } => suggestion.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TzError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            TzError::NoDatabase => fl!("tz-error-no-database"),
+            TzError::NotChosen => fl!("tz-error-not-chosen"),
+            TzError::NotFound {
+                name,
+                suggestion: Some(suggestion),
+            } => fl!(
+                "tz-error-not-found-suggestion",
+                name = name.as_str(),
+                suggestion = suggestion.as_str()
+            ),
+            TzError::NotFound {
+                name,
+                suggestion: None,
+            } => fl!("tz-error-not-found", name = name.as_str()),
+            TzError::PermissionDenied { name } => {
+                fl!("tz-error-permission-denied", name = name.as_str())
+            }
+            TzError::Corrupt { name, .. } => fl!("tz-error-corrupt", name = name.as_str()),
+            TzError::Unsupported { name } => fl!("tz-error-unsupported", name = name.as_str()),
+            TzError::InvalidName { name, .. } => fl!("tz-error-invalid-name", name = name.as_str()),
+            TzError::OutsideRoot { name } => {
+                fl!("tz-error-outside-root", name = name.as_str())
+            }
+            TzError::Io { name, reason } => {
+                fl!(
+                    "tz-error-io",
+                    name = name.as_str(),
+                    reason = reason.as_str()
+                )
+            }
+        };
+        f.write_str(&msg)
+    }
+}
+
+impl std::error::Error for TzError {}
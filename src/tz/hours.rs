@@ -0,0 +1,159 @@
+use chrono::{Datelike, NaiveDateTime, NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+
+/// When someone in a clock's zone is about, in their own local time.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WorkingHours {
+    pub start: NaiveTime,
+    /// Before `start` for a shift that runs past midnight.
+    pub end: NaiveTime,
+    /// Weekends vary, e.g. Friday and Saturday in parts of the Middle East.
+    pub days: Vec<Weekday>,
+    /// Asleep on any day, unless working. Can also run past midnight.
+    pub sleep_start: NaiveTime,
+    pub sleep_end: NaiveTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Availability {
+    Working,
+    OffHours,
+    Asleep,
+}
+
+impl Default for WorkingHours {
+    fn default() -> Self {
+        let hm = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        WorkingHours {
+            start: hm(9, 0),
+            end: hm(17, 0),
+            days: vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ],
+            sleep_start: hm(23, 0),
+            sleep_end: hm(7, 0),
+        }
+    }
+}
+
+impl WorkingHours {
+    /// Availability at a local wall-clock time.
+    pub fn availability(&self, local: NaiveDateTime) -> Availability {
+        let time = local.time();
+        // The small hours of a shift that started yesterday count as yesterday's.
+        let shift_day = if self.end < self.start && time < self.end {
+            local.weekday().pred()
+        } else {
+            local.weekday()
+        };
+        if self.days.contains(&shift_day) && within(time, self.start, self.end) {
+            Availability::Working
+        } else if within(time, self.sleep_start, self.sleep_end) {
+            Availability::Asleep
+        } else {
+            Availability::OffHours
+        }
+    }
+}
+
+/// Whether `time` is in `[start, end)`, which wraps past midnight if `end` is earlier.
+fn within(time: NaiveTime, start: NaiveTime, end: NaiveTime) -> bool {
+    if start <= end {
+        start <= time && time < end
+    } else {
+        start <= time || time < end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Availability::*;
+
+    fn hm(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, m, 0).unwrap()
+    }
+
+    /// 1 January 2024 was a Monday.
+    fn check(hours: &WorkingHours, cases: &[(&str, Availability)]) {
+        for &(local, expected) in cases {
+            let local: NaiveDateTime = local.parse().unwrap();
+            assert_eq!(hours.availability(local), expected, "at {}", local);
+        }
+    }
+
+    #[test]
+    fn start_and_end_boundaries() {
+        check(
+            &WorkingHours::default(),
+            &[
+                ("2024-01-01T08:59:59", OffHours),
+                ("2024-01-01T09:00:00", Working),
+                ("2024-01-01T16:59:59", Working),
+                ("2024-01-01T17:00:00", OffHours),
+                ("2024-01-06T12:00:00", OffHours),
+            ],
+        );
+    }
+
+    #[test]
+    fn sleep_crosses_midnight() {
+        check(
+            &WorkingHours::default(),
+            &[
+                ("2024-01-01T22:59:59", OffHours),
+                ("2024-01-01T23:00:00", Asleep),
+                ("2024-01-02T03:00:00", Asleep),
+                ("2024-01-02T06:59:59", Asleep),
+                ("2024-01-02T07:00:00", OffHours),
+            ],
+        );
+    }
+
+    #[test]
+    fn overnight_shift_belongs_to_the_day_it_started() {
+        let hours = WorkingHours {
+            start: hm(22, 0),
+            end: hm(6, 0),
+            ..Default::default()
+        };
+        check(
+            &hours,
+            &[
+                ("2024-01-01T21:59:59", OffHours),
+                ("2024-01-01T22:00:00", Working),
+                ("2024-01-02T05:59:59", Working),
+                ("2024-01-02T06:00:00", Asleep),
+                // Friday night's shift runs into Saturday morning.
+                ("2024-01-06T03:00:00", Working),
+                // Nobody started one on Saturday or Sunday night.
+                ("2024-01-06T23:00:00", Asleep),
+                ("2024-01-01T03:00:00", Asleep),
+                ("2024-01-01T12:00:00", OffHours),
+            ],
+        );
+    }
+
+    #[test]
+    fn friday_saturday_weekend() {
+        use Weekday::*;
+        let hours = WorkingHours {
+            days: vec![Sun, Mon, Tue, Wed, Thu],
+            ..Default::default()
+        };
+        check(
+            &hours,
+            &[
+                ("2024-01-04T10:00:00", Working),
+                ("2024-01-05T10:00:00", OffHours),
+                ("2024-01-06T10:00:00", OffHours),
+                ("2024-01-07T10:00:00", Working),
+            ],
+        );
+    }
+}
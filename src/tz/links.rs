@@ -0,0 +1,161 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+use super::TzSource;
+
+/// Zones in their own right that no `.tab` file lists, so a hardlink group has to be told
+/// they're the real one. Otherwise `Etc/UCT` would win over `Etc/UTC` on name order.
+const UNLISTED_ZONES: &[&str] = &["Etc/UTC", "Etc/GMT"];
+
+/// Finds which zone names are links (aliases) to another zone, returning `link -> canonical`.
+///
+/// `Link` lines from `tzdata.zi` / `backward` are authoritative. Distros that don't ship those
+/// still install links as symlinks or hardlinks, so we fall back to the filesystem.
+/// `is_listed` says whether a name is in `zone1970.tab` / `zone.tab`, which makes it the better
+/// pick when a hardlink group has nothing else to go on.
+pub fn find_links(
+    source: &TzSource,
+    names: &[String],
+    is_listed: impl Fn(&str) -> bool,
+) -> HashMap<String, String> {
+    let mut links = HashMap::new();
+    for file in ["tzdata.zi", "backward"] {
+        if let Ok(bytes) = source.read(file) {
+            parse_links(&String::from_utf8_lossy(&bytes), &mut links);
+        }
+    }
+    match source {
+        TzSource::System(root) => filesystem_links(&root.path, names, &is_listed, &mut links),
+        #[cfg(feature = "bundled-tzdata")]
+        TzSource::Bundled => {}
+    }
+
+    let known: HashSet<&str> = names.iter().map(String::as_str).collect();
+    resolve_chains(links)
+        .into_iter()
+        .filter(|(link, target)| known.contains(link.as_str()) && known.contains(target.as_str()))
+        .collect()
+}
+
+/// `Link TARGET LINK` in tzdb source files, abbreviated to `L` in `tzdata.zi`.
+fn parse_links(s: &str, links: &mut HashMap<String, String>) {
+    for line in s.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut cols = line.split_whitespace();
+        if !matches!(cols.next(), Some("L" | "Link")) {
+            continue;
+        }
+        if let (Some(target), Some(link)) = (cols.next(), cols.next()) {
+            links.insert(link.to_owned(), target.to_owned());
+        }
+    }
+}
+
+fn filesystem_links(
+    root: &Path,
+    names: &[String],
+    is_listed: &impl Fn(&str) -> bool,
+    links: &mut HashMap<String, String>,
+) {
+    let Ok(canonical_root) = fs::canonicalize(root) else {
+        return;
+    };
+    let targets: HashSet<String> = links.values().cloned().collect();
+    let mut by_inode: HashMap<(u64, u64), Vec<&str>> = HashMap::new();
+
+    for name in names {
+        if links.contains_key(name) {
+            continue;
+        }
+        let path = root.join(name);
+        let Ok(meta) = fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if meta.file_type().is_symlink() {
+            let target = fs::canonicalize(&path).ok().and_then(|t| {
+                let rel = t.strip_prefix(&canonical_root).ok()?;
+                Some(rel.to_string_lossy().into_owned())
+            });
+            match target {
+                Some(target) if &target != name => {
+                    links.insert(name.clone(), target);
+                }
+                _ => {}
+            }
+        } else if meta.nlink() > 1 {
+            by_inode
+                .entry((meta.dev(), meta.ino()))
+                .or_default()
+                .push(name);
+        }
+    }
+
+    for group in by_inode.into_values().filter(|g| g.len() > 1) {
+        let Some(canonical) = group.iter().copied().max_by_key(|n| {
+            (
+                targets.contains(*n),
+                is_listed(n) || UNLISTED_ZONES.contains(n),
+                n.contains('/'),
+                std::cmp::Reverse(*n),
+            )
+        }) else {
+            continue;
+        };
+        for name in group.into_iter().filter(|n| *n != canonical) {
+            links.insert(name.to_owned(), canonical.to_owned());
+        }
+    }
+}
+
+/// Point every link straight at its final target, dropping any cycles.
+fn resolve_chains(links: HashMap<String, String>) -> HashMap<String, String> {
+    links
+        .iter()
+        .filter_map(|(link, target)| {
+            let mut target = target;
+            for _ in 0..8 {
+                match links.get(target) {
+                    Some(next) => target = next,
+                    None => return (target != link).then(|| (link.clone(), target.clone())),
+                }
+            }
+            None
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hardlinked_utc_keeps_its_zone_name() {
+        let root = std::env::temp_dir().join(format!("world-clocks-links-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("Etc")).unwrap();
+        fs::write(root.join("Etc/UTC"), b"TZif2").unwrap();
+        let names: Vec<String> = ["Etc/UCT", "Etc/UTC", "Etc/Universal", "UTC", "Zulu"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        for name in names.iter().filter(|n| *n != "Etc/UTC") {
+            fs::hard_link(root.join("Etc/UTC"), root.join(name)).unwrap();
+        }
+
+        let mut links = HashMap::new();
+        filesystem_links(&root, &names, &|_: &str| false, &mut links);
+        let _ = fs::remove_dir_all(&root);
+
+        assert!(!links.contains_key("Etc/UTC"));
+        for name in names.iter().filter(|n| *n != "Etc/UTC") {
+            assert_eq!(
+                links.get(name).map(String::as_str),
+                Some("Etc/UTC"),
+                "{}",
+                name
+            );
+        }
+    }
+}
@@ -0,0 +1,595 @@
+use chrono::format::{Item, Numeric, StrftimeItems};
+use chrono::{DateTime, TimeZone, Utc};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex, OnceLock, RwLock};
+use walkdir::WalkDir;
+
+mod catalog;
+mod cities;
+mod error;
+mod hours;
+mod links;
+mod posix;
+mod renames;
+mod watch;
+mod zone;
+pub use catalog::{Catalog, CatalogEntry, Coordinates, ZoneInfo};
+pub use error::TzError;
+pub use hours::{Availability, WorkingHours};
+use posix::PosixTz;
+pub use watch::watch;
+pub use zone::Transition;
+use zone::{fixed_offset_name, parse_fixed_offset, Zone};
+
+pub struct ValidTz {
+    #[allow(dead_code)]
+    pub name: String,
+    pub display_name: String,
+    /// Shared with every other clock on the same zone, see [`cached_zone`].
+    pub tz: Arc<Zone>,
+}
+
+/// Where we found the zoneinfo root.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RootOrigin {
+    Config,
+    Env,
+    SearchPath,
+}
+
+#[derive(Debug, Clone)]
+pub struct ZoneinfoRoot {
+    pub path: PathBuf,
+    pub origin: RootOrigin,
+}
+
+/// Where zone data is actually read from.
+pub enum TzSource {
+    System(ZoneinfoRoot),
+    /// The system root is missing or empty, so use the copy compiled into the binary.
+    #[cfg(feature = "bundled-tzdata")]
+    Bundled,
+}
+
+/// A pinned tzdata release compiled into `res/zoneinfo`, see `just update-tzdata`.
+#[cfg(feature = "bundled-tzdata")]
+#[derive(rust_embed::RustEmbed)]
+#[folder = "res/zoneinfo/"]
+struct BundledZoneinfo;
+
+/// Places tzdata commonly lives when neither the config nor `TZDIR` say otherwise.
+static SEARCH_PATHS: &[&str] = &[
+    "/usr/share/zoneinfo",
+    "/usr/lib/zoneinfo",
+    "/usr/share/lib/zoneinfo",
+    "/etc/zoneinfo",
+];
+
+static TITLE_CASE: LazyLock<Regex> = LazyLock::new(|| Regex::new("^[A-Z]").unwrap());
+/// The tzdb naming rules: `/`-separated components of ASCII letters, digits, `_`, `+` and `-`,
+/// not starting with `-`. That rules out `.`, `..` and absolute paths.
+/// Anything else can't be a zoneinfo path, so we try it as a POSIX TZ string.
+static TZDB_NAME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^[A-Za-z0-9_+][A-Za-z0-9_+\-]*(/[A-Za-z0-9_+][A-Za-z0-9_+\-]*)*$").unwrap()
+});
+static CONFIGURED_ROOT: OnceLock<Option<PathBuf>> = OnceLock::new();
+/// Resolved once, so listing and loading always agree on where zones come from.
+pub static TZ_ROOT: LazyLock<Option<ZoneinfoRoot>> = LazyLock::new(ZoneinfoRoot::find);
+pub static TZ_SOURCE: LazyLock<Option<TzSource>> = LazyLock::new(TzSource::find);
+/// Empty until [`reload`] has finished, walking the whole tree is too slow to do at startup.
+static CATALOG: RwLock<Option<Arc<Catalog>>> = RwLock::new(None);
+/// Parsed zones by name, so the panel and editor don't each re-read the same files.
+/// Failures aren't cached, they might be fixed by the next tzdata or config change.
+static ZONES: LazyLock<Mutex<HashMap<String, Arc<Zone>>>> = LazyLock::new(Default::default);
+
+/// Set the zoneinfo root from the config. Must be called before anything touches [`TZ_ROOT`].
+pub fn configure_root(path: Option<String>) {
+    let path = path.filter(|p| !p.is_empty()).map(PathBuf::from);
+    if CONFIGURED_ROOT.set(path).is_err() {
+        tracing::warn!("zoneinfo root configured twice, ignoring");
+    }
+}
+
+impl ZoneinfoRoot {
+    fn find() -> Option<Self> {
+        let configured = CONFIGURED_ROOT.get().cloned().flatten();
+        let from_env = std::env::var_os("TZDIR")
+            .filter(|p| !p.is_empty())
+            .map(PathBuf::from);
+
+        let candidates = configured
+            .map(|p| (p, RootOrigin::Config))
+            .into_iter()
+            .chain(from_env.map(|p| (p, RootOrigin::Env)))
+            .chain(
+                SEARCH_PATHS
+                    .iter()
+                    .map(|p| (PathBuf::from(p), RootOrigin::SearchPath)),
+            );
+
+        for (path, origin) in candidates {
+            if path.is_dir() {
+                tracing::info!(?path, ?origin, "Using zoneinfo root");
+                return Some(ZoneinfoRoot { path, origin });
+            }
+            if origin != RootOrigin::SearchPath {
+                tracing::warn!(?path, ?origin, "zoneinfo root is not a directory, skipping");
+            }
+        }
+        tracing::error!("No zoneinfo root found");
+        None
+    }
+
+    /// Where `name` lives under the root. Refuses anything that ends up outside it,
+    /// whether through `..`, an absolute path or a symlink.
+    fn resolve(&self, name: &str) -> Result<PathBuf, TzError> {
+        let root = std::fs::canonicalize(&self.path).map_err(|e| TzError::from_io(name, e))?;
+        let path =
+            std::fs::canonicalize(self.path.join(name)).map_err(|e| TzError::from_io(name, e))?;
+        if !path.starts_with(&root) {
+            tracing::warn!(
+                name,
+                ?path,
+                "Zone resolves outside the zoneinfo root, refusing"
+            );
+            return Err(TzError::OutsideRoot {
+                name: name.to_owned(),
+            });
+        }
+        Ok(path)
+    }
+
+    /// Etc/UTC is in every tzdata build, if it's missing the root is empty or incomplete.
+    /// Looks on disk rather than using [`TzSource::read`], which would find the bundled copy.
+    fn is_usable(&self) -> bool {
+        self.resolve("Etc/UTC").is_ok_and(|path| path.is_file())
+    }
+}
+
+impl fmt::Display for ZoneinfoRoot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let origin = match self.origin {
+            RootOrigin::Config => "from config",
+            RootOrigin::Env => "from TZDIR",
+            RootOrigin::SearchPath => "default",
+        };
+        write!(f, "{} ({})", self.path.display(), origin)
+    }
+}
+
+impl TzSource {
+    fn find() -> Option<Self> {
+        Self::choose(TZ_ROOT.clone())
+    }
+
+    /// The system root, unless it's unusable and we have a bundled copy to use instead.
+    fn choose(root: Option<ZoneinfoRoot>) -> Option<Self> {
+        let usable = root.as_ref().is_some_and(ZoneinfoRoot::is_usable);
+        #[cfg(feature = "bundled-tzdata")]
+        if !usable {
+            tracing::warn!("System zoneinfo missing or incomplete, using bundled tz database");
+            return Some(TzSource::Bundled);
+        }
+        if !usable && root.is_some() {
+            tracing::warn!("System zoneinfo is incomplete, some clocks may not load");
+        }
+        root.map(TzSource::System)
+    }
+
+    fn read(&self, name: &str) -> Result<Vec<u8>, TzError> {
+        match self {
+            TzSource::System(root) => {
+                let res = root
+                    .resolve(name)
+                    .and_then(|path| std::fs::read(path).map_err(|e| TzError::from_io(name, e)));
+                #[cfg(feature = "bundled-tzdata")]
+                if let Err(TzError::NotFound { .. }) = &res {
+                    // A trimmed system tzdata may lack zones the bundled copy has.
+                    if let Ok(bytes) = TzSource::Bundled.read(name) {
+                        tracing::debug!(name, "Zone missing from system root, using bundled copy");
+                        return Ok(bytes);
+                    }
+                }
+                res
+            }
+            #[cfg(feature = "bundled-tzdata")]
+            TzSource::Bundled => BundledZoneinfo::get(name)
+                .map(|f| f.data.into_owned())
+                .ok_or_else(|| TzError::NotFound {
+                    name: name.to_owned(),
+                    suggestion: None,
+                }),
+        }
+    }
+
+    /// The tzdata release, e.g. "2025b", from `version` or else the `tzdata.zi` header.
+    /// Unlike [`Self::read`] this never falls back to the bundled copy, so it's the real one.
+    fn version(&self) -> Option<String> {
+        let read = |file: &str| match self {
+            TzSource::System(root) => std::fs::read_to_string(root.path.join(file)).ok(),
+            #[cfg(feature = "bundled-tzdata")]
+            TzSource::Bundled => {
+                BundledZoneinfo::get(file).map(|f| String::from_utf8_lossy(&f.data).into_owned())
+            }
+        };
+        let from_file = read("version").map(|s| s.trim().to_owned());
+        from_file
+            .or_else(|| {
+                let zi = read("tzdata.zi")?;
+                Some(
+                    zi.lines()
+                        .next()?
+                        .strip_prefix("# version ")?
+                        .trim()
+                        .to_owned(),
+                )
+            })
+            .filter(|v| !v.is_empty())
+    }
+
+    fn names(&self) -> Vec<String> {
+        match self {
+            TzSource::System(root) => list_dir(&root.path),
+            #[cfg(feature = "bundled-tzdata")]
+            TzSource::Bundled => BundledZoneinfo::iter()
+                .filter(|p| p.split('/').all(|c| TITLE_CASE.is_match(c)))
+                .map(|p| p.into_owned())
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for TzSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TzSource::System(root) => root.fmt(f),
+            #[cfg(feature = "bundled-tzdata")]
+            TzSource::Bundled => write!(f, "bundled with World Clocks"),
+        }
+    }
+}
+
+impl ValidTz {
+    pub fn from_names(name: &str, display_name: &str) -> Result<Self, TzError> {
+        let tz = cached_zone(name).inspect_err(|err| {
+            tracing::debug!(name, ?err, "Couldn't load timezone");
+        })?;
+        Ok(ValidTz {
+            name: name.to_owned(),
+            display_name: display_name.to_owned(),
+            tz,
+        })
+    }
+
+    /// `at` in this zone, strftime-style. Falls back to [`DEFAULT_FORMAT`] if `format` is invalid.
+    pub fn format(&self, at: DateTime<Utc>, format: &str) -> String {
+        let at = at.with_timezone(&&*self.tz);
+        try_format(&at, format).unwrap_or_else(|| at.format(DEFAULT_FORMAT).to_string())
+    }
+
+    /// The tzdb abbreviation in effect at `at`, e.g. "AEST", or "+0530" where tzdb has none.
+    pub fn abbreviation(&self, at: DateTime<Utc>) -> String {
+        at.with_timezone(&&*self.tz).offset().to_string()
+    }
+
+    /// Whether it's working hours, off hours or the middle of the night at `at`.
+    pub fn availability(&self, at: DateTime<Utc>, hours: &WorkingHours) -> Availability {
+        hours.availability(at.with_timezone(&&*self.tz).naive_local())
+    }
+
+    /// The next change in UTC offset after `from`, if there is one within about a year.
+    pub fn next_transition(&self, from: DateTime<Utc>) -> Option<Transition> {
+        self.tz.next_transition(from)
+    }
+
+    /// The last change in UTC offset before `from`, if there was one within about a year.
+    pub fn previous_transition(&self, from: DateTime<Utc>) -> Option<Transition> {
+        self.tz.previous_transition(from)
+    }
+}
+
+/// How clocks are shown when nothing says otherwise.
+pub const DEFAULT_FORMAT: &str = "%H:%M";
+
+/// Whether chrono can write a strftime-style format. `to_string()` panics on one it can't,
+/// and that includes parse-only specifiers like `%#z`, not just ones it doesn't know.
+pub fn is_valid_format(format: &str) -> bool {
+    try_format(&Utc::now(), format).is_some()
+}
+
+/// `at` formatted strftime-style, or `None` if chrono can't write `format`.
+pub fn try_format<Tz: TimeZone>(at: &DateTime<Tz>, format: &str) -> Option<String>
+where
+    Tz::Offset: fmt::Display,
+{
+    let mut s = String::new();
+    write!(s, "{}", at.format(format)).ok()?;
+    Some(s)
+}
+
+/// Whether a format shows seconds, so the clock needs to tick every second.
+pub fn format_has_seconds(format: &str) -> bool {
+    StrftimeItems::new(format).any(|item| {
+        matches!(
+            item,
+            Item::Numeric(
+                Numeric::Second | Numeric::Nanosecond | Numeric::Timestamp,
+                _
+            )
+        )
+    })
+}
+
+/// The current zone catalog, or `None` while it's still loading.
+/// Hold on to the `Arc` rather than calling this in a loop.
+pub fn catalog() -> Option<Arc<Catalog>> {
+    CATALOG.read().unwrap().clone()
+}
+
+/// Reads the zone list and tables on a blocking thread, at startup and after a tzdata update.
+/// The old catalog stays in place until the new one is ready.
+pub async fn reload() {
+    let fresh = tokio::task::spawn_blocking(load_catalog)
+        .await
+        .unwrap_or_else(|err| {
+            tracing::error!(?err, "Loading the timezone catalog failed");
+            Catalog::default()
+        });
+    *CATALOG.write().unwrap() = Some(Arc::new(fresh));
+    // Zones parsed from the old tzdata are stale now.
+    ZONES.lock().unwrap().clear();
+}
+
+fn load_catalog() -> Catalog {
+    match TZ_SOURCE.as_ref() {
+        Some(source) => Catalog::load(source, &source.names()),
+        None => Catalog::default(),
+    }
+}
+
+fn list_dir(root: &Path) -> Vec<String> {
+    let tzs: Vec<String> = WalkDir::new(root)
+        .min_depth(1)
+        .max_depth(4)
+        .into_iter()
+        .filter_entry(|p| {
+            let _fn = p.file_name();
+
+            p.file_name()
+                .to_str()
+                .map(|s| TITLE_CASE.is_match(s))
+                .unwrap_or(false)
+        })
+        .filter_map(|e| -> Option<_> {
+            let entry = e.ok()?;
+            // Some distros install links as symlinks, we want those too.
+            let is_file =
+                entry.file_type().is_file() || (entry.path_is_symlink() && entry.path().is_file());
+            if !is_file {
+                return None;
+            }
+            let rel_path = entry.path().strip_prefix(root).ok()?;
+            return Some(rel_path.to_string_lossy().to_string());
+        })
+        .collect();
+    return tzs;
+}
+
+/// A fixed offset typed into the picker, e.g. `UTC+5:45`, `utc-3` or `+05:45`, as the zone
+/// name we store for it.
+pub fn typed_offset(input: &str) -> Option<String> {
+    let input = input.trim().to_uppercase();
+    let name = if input.starts_with(['+', '-']) {
+        format!("UTC{}", input)
+    } else {
+        input
+    };
+    parse_fixed_offset(&name).map(fixed_offset_name)
+}
+
+/// A reasonable display name for a zone, e.g. `America/New_York` gives "New York".
+pub fn default_display_name(name: &str) -> String {
+    if let Some(offset) = parse_fixed_offset(name) {
+        return fixed_offset_name(offset);
+    }
+    if !TZDB_NAME.is_match(name) {
+        if let Ok(posix) = PosixTz::parse(name) {
+            return posix.std_abbreviation().to_owned();
+        }
+    }
+    name.rsplit('/').next().unwrap_or(name).replace('_', " ")
+}
+
+fn cached_zone(name: &str) -> Result<Arc<Zone>, TzError> {
+    if let Some(zone) = ZONES.lock().unwrap().get(name) {
+        return Ok(zone.clone());
+    }
+    let zone = Arc::new(load_zone(name)?);
+    ZONES.lock().unwrap().insert(name.to_owned(), zone.clone());
+    Ok(zone)
+}
+
+fn load_zone(name: &str) -> Result<Zone, TzError> {
+    if let Some(offset) = parse_fixed_offset(name) {
+        return Ok(Zone::Fixed(offset));
+    }
+    let posix = || {
+        PosixTz::parse(name).map_err(|e| TzError::InvalidName {
+            name: name.to_owned(),
+            reason: format!("{:#}", e),
+        })
+    };
+    // Typos and city names make it this far too, so say what we'd have picked instead.
+    let not_found = |e: TzError| match suggest(name) {
+        Some(suggestion) => TzError::NotFound {
+            name: name.to_owned(),
+            suggestion: Some(suggestion),
+        },
+        None => e,
+    };
+    if !TZDB_NAME.is_match(name) {
+        return posix().map(Zone::Posix).map_err(not_found);
+    }
+    let source = TZ_SOURCE.as_ref().ok_or(TzError::NoDatabase)?;
+    match load_tz(source, name) {
+        Ok(tz) => Ok(Zone::Tzif(tz)),
+        // Short rule strings like `EST5` or `UTC0` look like paths too.
+        Err(e @ TzError::NotFound { .. }) => posix().map(Zone::Posix).map_err(|_| not_found(e)),
+        Err(e) => posix().map(Zone::Posix).map_err(|_| e),
+    }
+}
+
+fn load_tz(source: &TzSource, name: &str) -> Result<tzfile::Tz, TzError> {
+    let bytes = source.read(name)?;
+    // tzfile would call anything without the TZif magic corrupt, so check what we've got first.
+    let version = match bytes.get(..5) {
+        Some([b'T', b'Z', b'i', b'f', version]) => Some(*version),
+        _ => None,
+    };
+    if !matches!(version, Some(0 | b'2' | b'3' | b'4')) {
+        return Err(TzError::Unsupported {
+            name: name.to_owned(),
+        });
+    }
+    tzfile::Tz::parse(name, &bytes).map_err(|e| TzError::Corrupt {
+        name: name.to_owned(),
+        reason: format!("{:?}", e),
+    })
+}
+
+/// What the user probably meant by a zone that doesn't exist: its new name if it was renamed,
+/// the same name in different case, or a zone named after the city they typed.
+fn suggest(name: &str) -> Option<String> {
+    let catalog = catalog()?;
+    if let Some(current) = catalog.current_name(name) {
+        return Some(current.to_owned());
+    }
+    let city = name.replace(' ', "_");
+    let is_city = |z: &&ZoneInfo| {
+        let last = z.name.rsplit('/').next().unwrap_or_default();
+        last.eq_ignore_ascii_case(&city)
+    };
+    let zones = || catalog.zones.iter().filter(|z| z.alias_of.is_none());
+    let found = zones()
+        .find(|z| z.name.eq_ignore_ascii_case(name))
+        .or_else(|| zones().find(is_city));
+    found.map(|z| z.name.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A scratch zoneinfo root, removed again even if the test fails.
+    struct TempRoot(PathBuf);
+
+    impl TempRoot {
+        fn new(test: &str) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("world-clocks-{}-{}", test, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            TempRoot(dir)
+        }
+
+        fn root(&self) -> ZoneinfoRoot {
+            ZoneinfoRoot {
+                path: self.0.clone(),
+                origin: RootOrigin::Config,
+            }
+        }
+    }
+
+    impl Drop for TempRoot {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn empty_system_root_isnt_used() {
+        let dir = TempRoot::new("empty-root");
+        assert!(!dir.root().is_usable());
+        #[cfg(feature = "bundled-tzdata")]
+        assert!(matches!(
+            TzSource::choose(Some(dir.root())),
+            Some(TzSource::Bundled)
+        ));
+
+        std::fs::create_dir(dir.0.join("Etc")).unwrap();
+        std::fs::write(dir.0.join("Etc/UTC"), b"TZif2").unwrap();
+        assert!(dir.root().is_usable());
+        assert!(matches!(
+            TzSource::choose(Some(dir.root())),
+            Some(TzSource::System(_))
+        ));
+    }
+
+    #[test]
+    fn names_that_escape_the_root_are_invalid() {
+        for name in ["../../etc/passwd", "/etc/passwd", "Europe/../../x"] {
+            assert!(!TZDB_NAME.is_match(name), "{:?}", name);
+            assert!(
+                matches!(load_zone(name), Err(TzError::InvalidName { .. })),
+                "{:?}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn paths_that_escape_the_root_are_refused() {
+        let dir = TempRoot::new("escape-root");
+        let outside = TempRoot::new("escape-target");
+        let target = outside.0.join("x");
+        std::fs::write(&target, b"TZif2").unwrap();
+        std::fs::create_dir_all(dir.0.join("Europe")).unwrap();
+        std::fs::create_dir_all(dir.0.join("Etc")).unwrap();
+        std::fs::write(dir.0.join("Etc/UTC"), b"TZif2").unwrap();
+        std::os::unix::fs::symlink(&target, dir.0.join("Europe/Link")).unwrap();
+        std::os::unix::fs::symlink("Etc/UTC", dir.0.join("UTC")).unwrap();
+
+        let sibling = outside.0.file_name().unwrap().to_str().unwrap();
+        let root = dir.root();
+        for name in [
+            format!("../{}/x", sibling),
+            format!("Europe/../../{}/x", sibling),
+            target.to_str().unwrap().to_owned(),
+            "Europe/Link".to_owned(),
+        ] {
+            assert!(
+                matches!(root.resolve(&name), Err(TzError::OutsideRoot { .. })),
+                "{:?}",
+                name
+            );
+        }
+        // Links within the root are fine.
+        assert_eq!(
+            root.resolve("UTC").unwrap(),
+            std::fs::canonicalize(dir.0.join("Etc/UTC")).unwrap()
+        );
+    }
+
+    #[test]
+    fn formats_chrono_cant_write_are_invalid() {
+        assert!(is_valid_format("%a %H:%M"));
+        assert!(is_valid_format("100%%"));
+        // Parse-only, it tokenizes fine but writing it fails.
+        assert!(!is_valid_format("%#z"));
+        assert!(!is_valid_format("%Q"));
+        assert!(!is_valid_format("%H:%M %"));
+    }
+
+    #[test]
+    fn invalid_formats_fall_back_to_the_default() {
+        let tz = ValidTz::from_names("UTC+05:30", "India").unwrap();
+        let at = "2024-01-01T00:00:00Z".parse().unwrap();
+        assert_eq!(tz.format(at, "%H:%M:%S"), "05:30:00");
+        for format in ["%#z", "%Q", "%H:%M %"] {
+            assert_eq!(tz.format(at, format), "05:30", "{:?}", format);
+        }
+    }
+}
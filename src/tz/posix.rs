@@ -0,0 +1,532 @@
+use anyhow::{bail, Context, Result};
+use chrono::{Datelike, NaiveDate, NaiveTime};
+
+/// A POSIX TZ rule string, e.g. `EST5EDT,M3.2.0,M11.1.0` or `<+0530>-5:30`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PosixTz {
+    std: Span,
+    dst: Option<Dst>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Span {
+    abbreviation: String,
+    /// Seconds east of UTC, i.e. the opposite sign to how POSIX writes it.
+    offset: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Dst {
+    span: Span,
+    start: Rule,
+    end: Rule,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rule {
+    date: RuleDate,
+    /// Local time of day in seconds, may be negative or past 24h.
+    time: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RuleDate {
+    /// `Jn`, 1-365, never counting Feb 29.
+    Julian1(u16),
+    /// `n`, 0-365, counting Feb 29.
+    Julian0(u16),
+    /// `Mm.w.d`, day `d` (0 = Sunday) of week `w` (5 = last) of month `m`.
+    MonthWeekDay { month: u32, week: u32, weekday: u32 },
+}
+
+impl PosixTz {
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut p = Parser { s, pos: 0 };
+        let tz = p.tz().context(format!("Invalid POSIX TZ string {}", s))?;
+        if p.pos != s.len() {
+            bail!("Invalid POSIX TZ string {}: trailing {}", s, &s[p.pos..]);
+        }
+        Ok(tz)
+    }
+
+    /// The UTC offset (seconds east) and abbreviation in effect at a UTC timestamp.
+    pub fn offset_at(&self, timestamp: i64) -> (i32, &str) {
+        let span = match &self.dst {
+            Some(dst) if dst.is_active(timestamp, self.std.offset) => &dst.span,
+            _ => &self.std,
+        };
+        (span.offset, &span.abbreviation)
+    }
+
+    /// The standard time abbreviation, e.g. `EST` for `EST5EDT`.
+    pub fn std_abbreviation(&self) -> &str {
+        &self.std.abbreviation
+    }
+
+    /// Every distinct offset this zone uses, for turning local times back into UTC.
+    pub fn offsets(&self) -> Vec<i32> {
+        let mut offsets = vec![self.std.offset];
+        if let Some(dst) = &self.dst {
+            offsets.push(dst.span.offset);
+        }
+        offsets
+    }
+}
+
+impl Dst {
+    fn is_active(&self, timestamp: i64, std_offset: i32) -> bool {
+        let year = chrono::DateTime::from_timestamp(timestamp + i64::from(std_offset), 0)
+            .map(|d| d.year())
+            .unwrap_or(1970);
+        // Rule times are in local time as it is before the transition.
+        let start = self.start.local_timestamp(year) - i64::from(std_offset);
+        let end = self.end.local_timestamp(year) - i64::from(self.span.offset);
+        if start < end {
+            start <= timestamp && timestamp < end
+        } else {
+            // Southern hemisphere, DST spans the new year.
+            timestamp < end || start <= timestamp
+        }
+    }
+}
+
+impl Rule {
+    fn local_timestamp(&self, year: i32) -> i64 {
+        let date = match self.date {
+            RuleDate::Julian1(n) => {
+                let leap = NaiveDate::from_ymd_opt(year, 2, 29).is_some();
+                let n = u64::from(n) - 1 + u64::from(leap && n >= 60);
+                jan1(year) + chrono::Days::new(n)
+            }
+            RuleDate::Julian0(n) => jan1(year) + chrono::Days::new(u64::from(n)),
+            RuleDate::MonthWeekDay {
+                month,
+                week,
+                weekday,
+            } => {
+                let first = NaiveDate::from_ymd_opt(year, month, 1).unwrap_or_else(|| jan1(year));
+                let first_weekday = first.weekday().num_days_from_sunday();
+                let mut day = 1 + (weekday + 7 - first_weekday) % 7 + (week - 1) * 7;
+                while NaiveDate::from_ymd_opt(year, month, day).is_none() {
+                    day -= 7;
+                }
+                NaiveDate::from_ymd_opt(year, month, day).unwrap_or(first)
+            }
+        };
+        date.and_time(NaiveTime::MIN).and_utc().timestamp() + i64::from(self.time)
+    }
+}
+
+fn jan1(year: i32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, 1, 1).unwrap_or_default()
+}
+
+struct Parser<'a> {
+    s: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn tz(&mut self) -> Result<PosixTz> {
+        let std = self.span(None)?;
+        if self.at_end() {
+            return Ok(PosixTz { std, dst: None });
+        }
+        let dst_span = self.span(Some(std.offset + 3600))?;
+        let (start, end) = if self.eat(',') {
+            let start = self.rule()?;
+            self.expect(',')?;
+            (start, self.rule()?)
+        } else {
+            // No rule given, POSIX leaves this up to us. Do what glibc does and use the US rules.
+            let us = |month, week| Rule {
+                date: RuleDate::MonthWeekDay {
+                    month,
+                    week,
+                    weekday: 0,
+                },
+                time: 2 * 3600,
+            };
+            (us(3, 2), us(11, 1))
+        };
+        Ok(PosixTz {
+            std,
+            dst: Some(Dst {
+                span: dst_span,
+                start,
+                end,
+            }),
+        })
+    }
+
+    /// An abbreviation and offset. The DST offset is optional, defaulting to an hour ahead.
+    fn span(&mut self, default_offset: Option<i32>) -> Result<Span> {
+        let abbreviation = self.abbreviation()?;
+        let offset = match default_offset {
+            Some(default) if self.at_end() || self.peek() == Some(',') => default,
+            _ => -self.time()?,
+        };
+        Ok(Span {
+            abbreviation,
+            offset,
+        })
+    }
+
+    fn abbreviation(&mut self) -> Result<String> {
+        let abbreviation = if self.eat('<') {
+            let len = self
+                .rest()
+                .find('>')
+                .context("unterminated <abbreviation>")?;
+            let abbreviation = &self.rest()[..len];
+            self.pos += len + 1;
+            abbreviation
+        } else {
+            let len = self
+                .rest()
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(self.rest().len());
+            let abbreviation = &self.rest()[..len];
+            self.pos += len;
+            abbreviation
+        };
+        if abbreviation.len() < 3 {
+            bail!("abbreviation must be at least 3 characters");
+        }
+        Ok(abbreviation.to_owned())
+    }
+
+    fn rule(&mut self) -> Result<Rule> {
+        let date = if self.eat('J') {
+            let n = self.number()?;
+            if !(1..=365).contains(&n) {
+                bail!("Julian day {} out of range", n);
+            }
+            RuleDate::Julian1(n as u16)
+        } else if self.eat('M') {
+            let month = self.number()?;
+            self.expect('.')?;
+            let week = self.number()?;
+            self.expect('.')?;
+            let weekday = self.number()?;
+            if !(1..=12).contains(&month) || !(1..=5).contains(&week) || weekday > 6 {
+                bail!("M{}.{}.{} out of range", month, week, weekday);
+            }
+            RuleDate::MonthWeekDay {
+                month,
+                week,
+                weekday,
+            }
+        } else {
+            let n = self.number()?;
+            if n > 365 {
+                bail!("day {} out of range", n);
+            }
+            RuleDate::Julian0(n as u16)
+        };
+        let time = if self.eat('/') {
+            self.time()?
+        } else {
+            2 * 3600
+        };
+        Ok(Rule { date, time })
+    }
+
+    /// `[+-]hh[:mm[:ss]]` in seconds.
+    fn time(&mut self) -> Result<i32> {
+        let sign = if self.eat('-') {
+            -1
+        } else {
+            self.eat('+');
+            1
+        };
+        let hours = self.number()?;
+        if hours > 167 {
+            bail!("hour {} out of range", hours);
+        }
+        let mut secs = hours as i32 * 3600;
+        for scale in [60, 1] {
+            if !self.eat(':') {
+                break;
+            }
+            let n = self.number()?;
+            if n > 59 {
+                bail!("{} out of range", n);
+            }
+            secs += n as i32 * scale;
+        }
+        Ok(sign * secs)
+    }
+
+    fn number(&mut self) -> Result<u32> {
+        let len = self
+            .rest()
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(self.rest().len());
+        let n = self.rest()[..len].parse().context("expected a number")?;
+        self.pos += len;
+        Ok(n)
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.s[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        if !self.eat(c) {
+            bail!("expected '{}'", c);
+        }
+        Ok(())
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos == self.s.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, LocalResult, NaiveDateTime, Offset, TimeZone, Utc};
+
+    use super::*;
+    use crate::tz::zone::Zone;
+
+    const NEW_YORK: &str = "EST5EDT,M3.2.0,M11.1.0";
+    const SYDNEY: &str = "AEST-10AEDT,M10.1.0,M4.1.0/3";
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    fn local(s: &str) -> NaiveDateTime {
+        s.parse().unwrap()
+    }
+
+    /// The offsets a local time could have, earliest instant first.
+    fn offsets(tz: &str, time: &str) -> LocalResult<i32> {
+        let zone = Zone::Posix(PosixTz::parse(tz).unwrap());
+        (&zone)
+            .offset_from_local_datetime(&local(time))
+            .map(|o| o.fix().local_minus_utc())
+    }
+
+    /// When a rule like `M3.2.0` falls in `year`, in local time.
+    fn rule_at(s: &str, year: i32) -> NaiveDateTime {
+        let mut p = Parser { s, pos: 0 };
+        let rule = p.rule().unwrap();
+        assert!(p.at_end(), "{} has trailing input", s);
+        let at = DateTime::from_timestamp(rule.local_timestamp(year), 0).unwrap();
+        at.naive_utc()
+    }
+
+    #[test]
+    fn parses_std_and_dst() {
+        let tz = PosixTz::parse(SYDNEY).unwrap();
+        assert_eq!(tz.std_abbreviation(), "AEST");
+        assert_eq!(tz.offsets(), vec![36000, 39600]);
+        let dst = tz.dst.unwrap();
+        assert_eq!(dst.span.abbreviation, "AEDT");
+        assert_eq!(dst.start.time, 2 * 3600);
+        assert_eq!(dst.end.time, 3 * 3600);
+    }
+
+    #[test]
+    fn parses_quoted_abbreviations_and_minutes() {
+        let tz = PosixTz::parse("<+0530>-5:30").unwrap();
+        assert_eq!(tz.std_abbreviation(), "+0530");
+        assert_eq!(tz.offsets(), vec![5 * 3600 + 30 * 60]);
+        assert_eq!(tz.offset_at(0), (19800, "+0530"));
+
+        let tz = PosixTz::parse("<-03>3<-02>,M3.5.0/-2,M10.5.0/-1").unwrap();
+        assert_eq!(tz.offsets(), vec![-3 * 3600, -2 * 3600]);
+        let dst = tz.dst.unwrap();
+        assert_eq!((dst.start.time, dst.end.time), (-2 * 3600, -3600));
+    }
+
+    #[test]
+    fn dst_defaults_to_an_hour_ahead_and_us_rules() {
+        let bare = PosixTz::parse("EST5EDT").unwrap();
+        assert_eq!(bare, PosixTz::parse(NEW_YORK).unwrap());
+        assert_eq!(bare.offsets(), vec![-5 * 3600, -4 * 3600]);
+    }
+
+    #[test]
+    fn month_week_day_rules() {
+        // 1 March 2024 was a Friday.
+        assert_eq!(rule_at("M3.2.0", 2024), local("2024-03-10T02:00:00"));
+        assert_eq!(rule_at("M11.1.0", 2024), local("2024-11-03T02:00:00"));
+        // Week 5 is the last one, whether the month has four or five of that weekday.
+        assert_eq!(rule_at("M10.5.0", 2024), local("2024-10-27T02:00:00"));
+        assert_eq!(rule_at("M3.5.0", 2024), local("2024-03-31T02:00:00"));
+        assert_eq!(rule_at("M2.5.4/1", 2024), local("2024-02-29T01:00:00"));
+        assert_eq!(rule_at("M2.5.4/1", 2023), local("2023-02-23T01:00:00"));
+    }
+
+    #[test]
+    fn julian_rules() {
+        // Jn never counts Feb 29, so J60 is always 1 March.
+        assert_eq!(rule_at("J60", 2023), local("2023-03-01T02:00:00"));
+        assert_eq!(rule_at("J60", 2024), local("2024-03-01T02:00:00"));
+        assert_eq!(rule_at("J365", 2024), local("2024-12-31T02:00:00"));
+        // n does, so 59 is Feb 29 in a leap year.
+        assert_eq!(rule_at("59", 2023), local("2023-03-01T02:00:00"));
+        assert_eq!(rule_at("59", 2024), local("2024-02-29T02:00:00"));
+        assert_eq!(rule_at("0/0", 2024), local("2024-01-01T00:00:00"));
+    }
+
+    #[test]
+    fn rule_times_past_midnight() {
+        assert_eq!(rule_at("J1/25", 2024), local("2024-01-02T01:00:00"));
+        assert_eq!(rule_at("J1/-1", 2024), local("2023-12-31T23:00:00"));
+    }
+
+    #[test]
+    fn northern_hemisphere_offsets() {
+        let tz = PosixTz::parse(NEW_YORK).unwrap();
+        let at = |s| tz.offset_at(utc(s).timestamp());
+        assert_eq!(at("2024-01-15T12:00:00Z"), (-5 * 3600, "EST"));
+        assert_eq!(at("2024-07-15T12:00:00Z"), (-4 * 3600, "EDT"));
+        assert_eq!(at("2024-03-10T06:59:59Z"), (-5 * 3600, "EST"));
+        assert_eq!(at("2024-03-10T07:00:00Z"), (-4 * 3600, "EDT"));
+    }
+
+    #[test]
+    fn southern_hemisphere_dst_wraps_the_new_year() {
+        let tz = PosixTz::parse(SYDNEY).unwrap();
+        let at = |s| tz.offset_at(utc(s).timestamp());
+        assert_eq!(at("2024-01-15T00:00:00Z"), (11 * 3600, "AEDT"));
+        assert_eq!(at("2024-07-15T00:00:00Z"), (10 * 3600, "AEST"));
+        assert_eq!(at("2024-12-31T13:00:00Z"), (11 * 3600, "AEDT"));
+        assert_eq!(at("2024-12-31T13:00:00Z"), at("2025-01-01T13:00:00Z"));
+    }
+
+    #[test]
+    fn new_york_transitions_to_the_second() {
+        let zone = Zone::Posix(PosixTz::parse(NEW_YORK).unwrap());
+
+        let spring = zone.next_transition(utc("2024-01-01T00:00:00Z")).unwrap();
+        assert_eq!(spring.at, utc("2024-03-10T07:00:00Z"));
+        assert_eq!(spring.before.local_minus_utc(), -5 * 3600);
+        assert_eq!(spring.after.local_minus_utc(), -4 * 3600);
+        assert_eq!(spring.abbreviation_after, "EDT");
+
+        let fall = zone.next_transition(spring.at).unwrap();
+        assert_eq!(fall.at, utc("2024-11-03T06:00:00Z"));
+        assert_eq!(fall.after.local_minus_utc(), -5 * 3600);
+        assert_eq!(fall.abbreviation_after, "EST");
+
+        // Sub-second starting points still land on the exact second.
+        let from = utc("2024-11-03T05:59:59.750Z");
+        assert_eq!(zone.next_transition(from).unwrap().at, fall.at);
+        let from = utc("2024-06-01T00:00:00Z");
+        assert_eq!(zone.previous_transition(from), Some(spring));
+    }
+
+    #[test]
+    fn sydney_transitions_to_the_second() {
+        let zone = Zone::Posix(PosixTz::parse(SYDNEY).unwrap());
+
+        let end = zone.next_transition(utc("2024-01-01T00:00:00Z")).unwrap();
+        assert_eq!(end.at, utc("2024-04-06T16:00:00Z"));
+        assert_eq!(end.before.local_minus_utc(), 11 * 3600);
+        assert_eq!(end.after.local_minus_utc(), 10 * 3600);
+        assert_eq!(end.abbreviation_after, "AEST");
+
+        let start = zone.next_transition(end.at).unwrap();
+        assert_eq!(start.at, utc("2024-10-05T16:00:00Z"));
+        assert_eq!(start.abbreviation_after, "AEDT");
+
+        // Looking back over the new year finds the previous October.
+        let from = utc("2024-01-01T00:00:00Z");
+        let previous = zone.previous_transition(from).unwrap();
+        assert_eq!(previous.at, utc("2023-09-30T16:00:00Z"));
+        assert_eq!(previous.before.local_minus_utc(), 10 * 3600);
+        assert_eq!(previous.after.local_minus_utc(), 11 * 3600);
+    }
+
+    #[test]
+    fn zones_without_dst_never_transition() {
+        let zone = Zone::Posix(PosixTz::parse("<+0530>-5:30").unwrap());
+        assert_eq!(zone.next_transition(utc("2024-01-01T00:00:00Z")), None);
+        assert_eq!(zone.previous_transition(utc("2024-01-01T00:00:00Z")), None);
+    }
+
+    #[test]
+    fn fall_back_is_ambiguous() {
+        // The earlier instant, still on summer time, comes first.
+        assert_eq!(
+            offsets(NEW_YORK, "2024-11-03T01:30:00"),
+            LocalResult::Ambiguous(-4 * 3600, -5 * 3600)
+        );
+        assert_eq!(
+            offsets(SYDNEY, "2024-04-07T02:30:00"),
+            LocalResult::Ambiguous(11 * 3600, 10 * 3600)
+        );
+        assert_eq!(
+            offsets(NEW_YORK, "2024-11-03T02:00:00"),
+            LocalResult::Single(-5 * 3600)
+        );
+    }
+
+    #[test]
+    fn spring_forward_is_a_gap() {
+        assert_eq!(offsets(NEW_YORK, "2024-03-10T02:30:00"), LocalResult::None);
+        assert_eq!(offsets(SYDNEY, "2024-10-06T02:30:00"), LocalResult::None);
+        assert_eq!(
+            offsets(NEW_YORK, "2024-03-10T03:00:00"),
+            LocalResult::Single(-4 * 3600)
+        );
+        assert_eq!(
+            offsets(NEW_YORK, "2024-03-10T01:59:59"),
+            LocalResult::Single(-5 * 3600)
+        );
+    }
+
+    #[test]
+    fn invalid_strings_are_errors() {
+        for s in [
+            "",
+            "EST",
+            "ES5",
+            "5EST",
+            "ÉST5",
+            "<EST5",
+            "<+5>-5",
+            "EST5x",
+            "EST168",
+            "EST5:60",
+            "EST5:",
+            "EST99999999999",
+            "EST5EDT,",
+            "EST5EDT,M3.2.0",
+            "EST5EDT,M3.2.0,",
+            "EST5EDT,M3.2.0,M11.1.0,",
+            "EST5EDT,M13.1.0,M11.1.0",
+            "EST5EDT,M0.1.0,M11.1.0",
+            "EST5EDT,M3.0.0,M11.1.0",
+            "EST5EDT,M3.6.0,M11.1.0",
+            "EST5EDT,M3.2.7,M11.1.0",
+            "EST5EDT,M3.2,M11.1.0",
+            "EST5EDT,J0,J365",
+            "EST5EDT,J366,J1",
+            "EST5EDT,366,0",
+            "EST5EDT,M3.2.0/,M11.1.0",
+            "EST5EDT,M3.2.0/168,M11.1.0",
+        ] {
+            assert!(PosixTz::parse(s).is_err(), "{:?} should be rejected", s);
+        }
+    }
+}
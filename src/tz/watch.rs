@@ -0,0 +1,26 @@
+use notify::{EventKind, RecursiveMode, Watcher};
+
+use super::{TzSource, TZ_SOURCE};
+
+/// Calls `on_change` whenever something under the zoneinfo root changes, until the returned
+/// watcher is dropped. Returns `None` if there's nothing on disk to watch.
+pub fn watch(on_change: impl Fn() + Send + 'static) -> Option<notify::RecommendedWatcher> {
+    let root = match TZ_SOURCE.as_ref()? {
+        TzSource::System(root) => root,
+        #[cfg(feature = "bundled-tzdata")]
+        TzSource::Bundled => return None,
+    };
+    let handler = move |res: notify::Result<notify::Event>| match res {
+        Ok(event) if !matches!(event.kind, EventKind::Access(_)) => on_change(),
+        Ok(_) => {}
+        Err(err) => tracing::warn!(?err, "Error watching zoneinfo root"),
+    };
+    let mut watcher = notify::recommended_watcher(handler)
+        .inspect_err(|err| tracing::error!(?err, "Couldn't watch zoneinfo root"))
+        .ok()?;
+    watcher
+        .watch(&root.path, RecursiveMode::Recursive)
+        .inspect_err(|err| tracing::error!(?err, "Couldn't watch zoneinfo root"))
+        .ok()?;
+    Some(watcher)
+}
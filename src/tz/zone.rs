@@ -0,0 +1,218 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::LazyLock;
+
+use chrono::{
+    DateTime, FixedOffset, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, Offset, SubsecRound,
+    TimeDelta, TimeZone, Utc,
+};
+
+use regex::Regex;
+
+use super::posix::PosixTz;
+
+/// The rules for a clock, however we got them.
+pub enum Zone {
+    /// A compiled tzdb zone.
+    Tzif(tzfile::Tz),
+    /// A POSIX TZ string, see [`PosixTz`].
+    Posix(PosixTz),
+    /// A fixed offset like `UTC+05:45`.
+    Fixed(FixedOffset),
+}
+
+/// `UTC+05:45`, `UTC-3`, `GMT+10:00`. Unlike POSIX and `Etc/GMT+N`, plus means east of UTC.
+static FIXED_OFFSET: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(?:UTC|GMT)([+-])([0-9]{1,2})(?::?([0-9]{2}))?$").unwrap());
+
+pub fn parse_fixed_offset(name: &str) -> Option<FixedOffset> {
+    let caps = FIXED_OFFSET.captures(name)?;
+    let hours: i32 = caps[2].parse().ok()?;
+    let minutes: i32 = caps.get(3).map_or(Ok(0), |m| m.as_str().parse()).ok()?;
+    if minutes >= 60 {
+        return None;
+    }
+    let secs = hours * 3600 + minutes * 60;
+    FixedOffset::east_opt(if &caps[1] == "-" { -secs } else { secs })
+}
+
+/// The canonical spelling of a fixed offset, e.g. `UTC+05:45`.
+pub fn fixed_offset_name(offset: FixedOffset) -> String {
+    let secs = offset.local_minus_utc();
+    let sign = if secs < 0 { '-' } else { '+' };
+    let secs = secs.abs();
+    format!("UTC{}{:02}:{:02}", sign, secs / 3600, secs % 3600 / 60)
+}
+
+/// The offset in effect somewhere in a [`Zone`]. Displays as the zone abbreviation, so `%Z` works.
+#[derive(Clone)]
+pub struct ZoneOffset<'a> {
+    zone: &'a Zone,
+    fix: FixedOffset,
+    abbreviation: Cow<'a, str>,
+}
+
+impl Offset for ZoneOffset<'_> {
+    fn fix(&self) -> FixedOffset {
+        self.fix
+    }
+}
+
+impl fmt::Debug for ZoneOffset<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.abbreviation, self.fix)
+    }
+}
+
+impl fmt::Display for ZoneOffset<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.abbreviation)
+    }
+}
+
+fn make_offset<'a>(
+    zone: &'a Zone,
+    fix: FixedOffset,
+    abbreviation: impl Into<Cow<'a, str>>,
+) -> ZoneOffset<'a> {
+    ZoneOffset {
+        zone,
+        fix,
+        abbreviation: abbreviation.into(),
+    }
+}
+
+/// Local to UTC by trying each offset the zone can have and keeping the ones that round-trip.
+fn local_from_offsets<'a>(
+    zone: &'a Zone,
+    local: &NaiveDateTime,
+    offsets: impl IntoIterator<Item = i32>,
+) -> LocalResult<ZoneOffset<'a>> {
+    let mut found: Vec<ZoneOffset<'a>> = offsets
+        .into_iter()
+        .filter_map(|secs| {
+            let utc = *local - chrono::TimeDelta::seconds(secs.into());
+            let offset = zone.offset_from_utc_datetime(&utc);
+            (offset.fix.local_minus_utc() == secs).then_some(offset)
+        })
+        .collect();
+    // Bigger offset means the earlier instant.
+    found.sort_by_key(|o| std::cmp::Reverse(o.fix.local_minus_utc()));
+    found.dedup_by_key(|o| o.fix);
+    match found.len() {
+        0 => LocalResult::None,
+        1 => LocalResult::Single(found.remove(0)),
+        _ => LocalResult::Ambiguous(found.remove(0), found.remove(0)),
+    }
+}
+
+fn fix(secs: i32) -> FixedOffset {
+    FixedOffset::east_opt(secs).unwrap_or_else(|| Utc.fix())
+}
+
+impl<'a> TimeZone for &'a Zone {
+    type Offset = ZoneOffset<'a>;
+
+    fn from_offset(offset: &Self::Offset) -> Self {
+        offset.zone
+    }
+
+    fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<Self::Offset> {
+        self.offset_from_local_datetime(&local.and_time(NaiveTime::MIN))
+    }
+
+    fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<Self::Offset> {
+        let zone: &'a Zone = *self;
+        match zone {
+            Zone::Tzif(tz) => tz
+                .offset_from_local_datetime(local)
+                .map(|o| make_offset(zone, o.fix(), o.to_string())),
+            Zone::Posix(tz) => local_from_offsets(zone, local, tz.offsets()),
+            Zone::Fixed(offset) => {
+                LocalResult::Single(make_offset(zone, *offset, fixed_offset_name(*offset)))
+            }
+        }
+    }
+
+    fn offset_from_utc_date(&self, utc: &NaiveDate) -> Self::Offset {
+        self.offset_from_utc_datetime(&utc.and_time(NaiveTime::MIN))
+    }
+
+    fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> Self::Offset {
+        let zone: &'a Zone = *self;
+        match zone {
+            Zone::Tzif(tz) => {
+                let o = tz.offset_from_utc_datetime(utc);
+                make_offset(zone, o.fix(), o.to_string())
+            }
+            Zone::Posix(tz) => {
+                let (secs, abbreviation) = tz.offset_at(utc.and_utc().timestamp());
+                make_offset(zone, fix(secs), abbreviation)
+            }
+            Zone::Fixed(offset) => make_offset(zone, *offset, fixed_offset_name(*offset)),
+        }
+    }
+}
+
+/// A change in a zone's UTC offset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transition {
+    pub at: DateTime<Utc>,
+    pub before: FixedOffset,
+    pub after: FixedOffset,
+    /// e.g. `BST` when London springs forward.
+    pub abbreviation_after: String,
+}
+
+/// How far to look for a transition. Every DST rule has one each way within a year.
+const SEARCH_DAYS: i64 = 400;
+
+impl Zone {
+    pub fn next_transition(&self, from: DateTime<Utc>) -> Option<Transition> {
+        self.find_transition(from, TimeDelta::days(1))
+    }
+
+    pub fn previous_transition(&self, from: DateTime<Utc>) -> Option<Transition> {
+        self.find_transition(from, TimeDelta::days(-1))
+    }
+
+    /// Steps a day at a time until the offset changes, then bisects down to the second.
+    /// No zone changes offset twice in a day, so stepping can't skip over one.
+    fn find_transition(&self, from: DateTime<Utc>, step: TimeDelta) -> Option<Transition> {
+        if let Zone::Fixed(_) = self {
+            return None;
+        }
+        let offset_at = |t: DateTime<Utc>| self.offset_from_utc_datetime(&t.naive_utc()).fix();
+        let from = from.trunc_subsecs(0);
+        let start = offset_at(from);
+        let mut prev = from;
+        for _ in 0..SEARCH_DAYS {
+            let next = prev + step;
+            if offset_at(next) != start {
+                // Order so offset_at(lo) is before the change and offset_at(hi) after it.
+                let (mut lo, mut hi) = if step > TimeDelta::zero() {
+                    (prev, next)
+                } else {
+                    (next, prev)
+                };
+                while hi - lo > TimeDelta::seconds(1) {
+                    let mid = lo + TimeDelta::seconds((hi - lo).num_seconds() / 2);
+                    if offset_at(mid) == offset_at(lo) {
+                        lo = mid;
+                    } else {
+                        hi = mid;
+                    }
+                }
+                let after = self.offset_from_utc_datetime(&hi.naive_utc());
+                return Some(Transition {
+                    at: hi,
+                    before: offset_at(lo),
+                    after: after.fix(),
+                    abbreviation_after: after.to_string(),
+                });
+            }
+            prev = next;
+        }
+        None
+    }
+}